@@ -0,0 +1,255 @@
+use cosmwasm_std::{Decimal, OverflowError, Uint128};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::state::{RootTally, SourceRoot, Strategy};
+
+/// Tally is the voting power behind every root proposed for a single chain.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Tally {
+    /// Power that voted for each root.
+    pub roots: BTreeMap<SourceRoot, Uint128>,
+    /// Power of every vote that included the chain.
+    pub participating_power: Uint128,
+    /// Power of the full validator set, whether or not it voted for the chain.
+    pub total_power: Uint128,
+}
+
+impl Tally {
+    pub fn new(total_power: Uint128) -> Self {
+        Tally {
+            total_power,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_vote(&mut self, root: SourceRoot, power: Uint128) -> Result<(), OverflowError> {
+        let root_power = self.roots.entry(root).or_default();
+        *root_power = root_power.checked_add(power)?;
+        self.participating_power = self.participating_power.checked_add(power)?;
+        Ok(())
+    }
+
+    /// leader returns the root with the most power. Ties go to the smallest root so the
+    /// result never depends on the order votes were received in.
+    pub fn leader(&self) -> Option<(&SourceRoot, Uint128)> {
+        self.roots
+            .iter()
+            .fold(None, |best, (root, power)| match best {
+                Some((_, best_power)) if best_power >= *power => best,
+                _ => Some((root, *power)),
+            })
+    }
+
+    /// ranked returns every root with its power, highest power first. Equal power is
+    /// ordered by root, like the leader.
+    pub fn ranked(&self) -> Vec<RootTally> {
+        let mut roots: Vec<RootTally> = self
+            .roots
+            .iter()
+            .map(|(root, power)| RootTally {
+                root: root.clone(),
+                power: *power,
+            })
+            .collect();
+        // stable, so roots of equal power stay in order
+        roots.sort_by_key(|root| Reverse(root.power));
+        roots
+    }
+
+    /// validator_set_power is the power of the full validator set. Votes carried over
+    /// from earlier blocks may come from validators missing in the current block, so it
+    /// is never less than the participating power.
+    pub fn validator_set_power(&self) -> Uint128 {
+        self.total_power.max(self.participating_power)
+    }
+}
+
+/// AggregationStrategy decides which root, if any, a chain finalizes given the tally
+/// of its votes and the quorum threshold configured for it.
+pub trait AggregationStrategy {
+    fn aggregate(&self, tally: &Tally, threshold: Decimal) -> Option<SourceRoot>;
+}
+
+/// SupermajorityPlurality finalizes the root with the most power once it holds
+/// `threshold` of the power that voted for the chain.
+pub struct SupermajorityPlurality;
+
+impl AggregationStrategy for SupermajorityPlurality {
+    fn aggregate(&self, tally: &Tally, threshold: Decimal) -> Option<SourceRoot> {
+        let (root, power) = tally.leader()?;
+        meets_threshold(power, tally.participating_power, threshold).then(|| root.clone())
+    }
+}
+
+/// Unanimity finalizes a root only when all of the power that voted for the chain
+/// agrees on it. The threshold is not used.
+pub struct Unanimity;
+
+impl AggregationStrategy for Unanimity {
+    fn aggregate(&self, tally: &Tally, _: Decimal) -> Option<SourceRoot> {
+        let (root, power) = tally.leader()?;
+        (!power.is_zero() && power == tally.participating_power).then(|| root.clone())
+    }
+}
+
+/// WeightedThreshold finalizes the root with the most power once it holds `threshold`
+/// of the full validator set, so validators that did not report the chain count
+/// against it.
+pub struct WeightedThreshold;
+
+impl AggregationStrategy for WeightedThreshold {
+    fn aggregate(&self, tally: &Tally, threshold: Decimal) -> Option<SourceRoot> {
+        let (root, power) = tally.leader()?;
+        meets_threshold(power, tally.validator_set_power(), threshold).then(|| root.clone())
+    }
+}
+
+/// FirstToQuorum finalizes the first root to hold `threshold` of the full validator
+/// set. The contract feeds it a tally that includes votes carried over from the
+/// previous blocks of the chain's window.
+pub struct FirstToQuorum;
+
+impl AggregationStrategy for FirstToQuorum {
+    fn aggregate(&self, tally: &Tally, threshold: Decimal) -> Option<SourceRoot> {
+        WeightedThreshold.aggregate(tally, threshold)
+    }
+}
+
+impl Strategy {
+    pub fn aggregation_strategy(&self) -> &'static dyn AggregationStrategy {
+        match self {
+            Strategy::SupermajorityPlurality => &SupermajorityPlurality,
+            Strategy::Unanimity => &Unanimity,
+            Strategy::WeightedThreshold => &WeightedThreshold,
+            Strategy::FirstToQuorum { .. } => &FirstToQuorum,
+        }
+    }
+}
+
+/// meets_threshold checks `power / total_power >= threshold` by cross-multiplying in
+/// 256 bits, which cannot overflow and avoids any rounding.
+pub fn meets_threshold(power: Uint128, total_power: Uint128, threshold: Decimal) -> bool {
+    !total_power.is_zero()
+        && power.full_mul(Decimal::one().atomics()) >= total_power.full_mul(threshold.atomics())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Binary;
+
+    fn source_root(root: &str) -> SourceRoot {
+        SourceRoot {
+            root: Binary::from(root.as_bytes()),
+            height: 1,
+        }
+    }
+
+    fn tally(total_power: u128, votes: &[(&str, u128)]) -> Tally {
+        let mut tally = Tally::new(Uint128::new(total_power));
+        for (root, power) in votes {
+            tally
+                .add_vote(source_root(root), Uint128::new(*power))
+                .unwrap();
+        }
+        tally
+    }
+
+    fn root(root: &str) -> Option<SourceRoot> {
+        Some(source_root(root))
+    }
+
+    #[test]
+    fn strategy_matrix() {
+        let two_thirds = Decimal::from_ratio(2u128, 3u128);
+        let strategies = [
+            Strategy::SupermajorityPlurality,
+            Strategy::Unanimity,
+            Strategy::WeightedThreshold,
+            Strategy::FirstToQuorum { window: 3 },
+        ];
+        // (description, tally, expected result per strategy in the order above)
+        let cases = [
+            ("no votes", tally(100, &[]), [None, None, None, None]),
+            (
+                "full agreement",
+                tally(100, &[("a", 100)]),
+                [root("a"), root("a"), root("a"), root("a")],
+            ),
+            (
+                "supermajority of participants and validator set",
+                tally(100, &[("a", 70), ("b", 30)]),
+                [root("a"), None, root("a"), root("a")],
+            ),
+            (
+                "unanimous but low participation",
+                tally(100, &[("a", 40)]),
+                [root("a"), root("a"), None, None],
+            ),
+            (
+                "supermajority of participants only",
+                tally(100, &[("a", 50), ("b", 10)]),
+                [root("a"), None, None, None],
+            ),
+            (
+                "exactly at the threshold",
+                tally(3, &[("a", 2), ("b", 1)]),
+                [root("a"), None, root("a"), root("a")],
+            ),
+            (
+                "split vote",
+                tally(100, &[("a", 50), ("b", 50)]),
+                [None, None, None, None],
+            ),
+            (
+                "zero power votes",
+                tally(0, &[("a", 0)]),
+                [None, None, None, None],
+            ),
+        ];
+
+        for (description, tally, expected) in cases {
+            for (strategy, expected) in strategies.iter().zip(expected) {
+                assert_eq!(
+                    strategy
+                        .aggregation_strategy()
+                        .aggregate(&tally, two_thirds),
+                    expected,
+                    "{description}: {strategy:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leader_breaks_ties_by_root() {
+        let tally = tally(100, &[("b", 50), ("a", 50)]);
+        assert_eq!(tally.leader(), Some((&source_root("a"), Uint128::new(50))));
+    }
+
+    #[test]
+    fn votes_for_different_heights_do_not_agree() {
+        let mut tally = tally(3, &[("a", 2)]);
+        let other_height = SourceRoot {
+            height: 2,
+            ..source_root("a")
+        };
+        tally.add_vote(other_height, Uint128::one()).unwrap();
+        assert_eq!(tally.roots.len(), 2);
+        assert_eq!(
+            SupermajorityPlurality.aggregate(&tally, Decimal::one()),
+            None
+        );
+    }
+
+    #[test]
+    fn carried_over_votes_extend_validator_set_power() {
+        let tally = tally(50, &[("a", 60), ("b", 10)]);
+        assert_eq!(tally.validator_set_power(), Uint128::new(70));
+        assert_eq!(
+            WeightedThreshold.aggregate(&tally, Decimal::percent(90)),
+            None
+        );
+    }
+}
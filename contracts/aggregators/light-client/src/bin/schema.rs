@@ -1,11 +1,13 @@
 use cosmwasm_schema::write_api;
 
-use aggregator::aggregator::QueryMsg;
-use slinky_avs_contracts::msg::InstantiateMsg;
+use slinky_avs_contracts::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg};
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
+        execute: ExecuteMsg,
         query: QueryMsg,
+        sudo: SudoMsg,
+        migrate: MigrateMsg,
     }
 }
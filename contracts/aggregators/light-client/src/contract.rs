@@ -1,59 +1,127 @@
 use crate::contract::execute::write_merkle_roots;
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    entry_point, to_json_binary, Addr, Attribute, BankMsg, Binary, BlockInfo, Coin, Decimal, Deps,
+    DepsMut, Empty, Env, Event, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Timestamp, Uint128,
 };
-use cw2::set_contract_version;
-use std::collections::BTreeMap;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::PrefixBound;
+use semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
 
+use crate::aggregation::{AggregationStrategy, Tally};
 use crate::error::{ContractError, ContractResult};
-use crate::msg::{ExecuteMsg, InstantiateMsg, SudoMsg, VoteExtension};
-use crate::state::{MERKLE_ROOTS, QUARUM, STAKE_MAP};
-use aggregator::aggregator::{LookupHashResponse, QueryMsg};
+use crate::helpers::{add_coins, weighted_power};
+use crate::migrations::MIGRATIONS;
+use crate::msg::{
+    ExecuteMsg, GenericVE, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg, Vote, VoteExtension,
+};
+use crate::state::{
+    Attestation, ChainConfig, ChainInfo, Config, FinalizedRoot, LivenessConfig, Ownership,
+    PendingVote, PowerSource, Retention, SlashFactor, SlashingConfig, SourceRoot, Strategy,
+    Unbonding, Voter, ATTESTATIONS, BONDED, CHAINS, CHAIN_CONFIGS, CONFIG, DELEGATED, DELEGATIONS,
+    DELEGATION_SLASH_FACTORS, JAILED, JAIL_RELEASES, OPERATORS, OPERATOR_PENDING_VOTES, OWNERSHIP,
+    PAUSED, PENDING_VOTES, ROOTS, ROOT_CURSORS, ROOT_INDEX, SLASH_FACTORS, STAKE_MAP, TOTAL_STAKE,
+    UNDELEGATIONS,
+};
+use crate::{liveness, prices, rewards};
+use aggregator::aggregator::{LookupHashResponse, LookupRootResponse};
 
 // version info for migration info
-const CONTRACT_NAME: &str = "crates.io:slinky-avs-contracts";
-const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-const CACHE_SIZE: usize = 6;
+pub(crate) const CONTRACT_NAME: &str = "crates.io:slinky-avs-contracts";
+pub(crate) const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub(crate) const CACHE_SIZE: u64 = 6;
+/// Maximum number of roots evicted by a single write. Lowering `max_roots` of a chain
+/// drains its excess roots over the following writes rather than all at once; they
+/// are already rejected by `LookupHash` in the meantime.
+const MAX_EVICTIONS_PER_WRITE: u64 = 2;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+/// Number of stakes a single `Reweight` recomputes the reward shares of by default
+/// and at most.
+const DEFAULT_REWEIGHT_LIMIT: u32 = 100;
+const MAX_REWEIGHT_LIMIT: u32 = 500;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> ContractResult<Response> {
     match msg {
-        ExecuteMsg::Stake { .. } => {
-            let sender_staked_coins = STAKE_MAP
-                .load(deps.storage, info.sender.clone())
-                .unwrap_or_else(|_| vec![]);
-
-            let updated_coins =
-                info.funds
-                    .iter()
-                    .fold(sender_staked_coins, |mut staked_coins, sent_coin| {
-                        match staked_coins
-                            .iter_mut()
-                            .find(|staked_coin| staked_coin.denom == sent_coin.denom)
-                        {
-                            Some(staked_coin) => {
-                                staked_coin.amount = staked_coin
-                                    .amount
-                                    .checked_add(sent_coin.amount)
-                                    .expect("Overflow in coin amount")
-                            }
-                            None => staked_coins.push(sent_coin.clone()),
-                        }
-                        staked_coins
-                    });
-
-            STAKE_MAP.save(deps.storage, info.sender, &updated_coins)?;
-
-            Ok(Response::new().add_attribute("action", "stake"))
+        ExecuteMsg::TransferOwnership { new_owner } => {
+            execute::transfer_ownership(deps, info, new_owner)
         }
-        ExecuteMsg::SubmitRoot { chain_id, root } => {
-            write_merkle_roots(deps, vec![(chain_id, root)])
+        ExecuteMsg::AcceptOwnership {} => execute::accept_ownership(deps, info),
+        ExecuteMsg::RenounceOwnership {} => execute::renounce_ownership(deps, info),
+        ExecuteMsg::SetPaused { paused } => execute::set_paused(deps, info, paused),
+        ExecuteMsg::Stake { .. } => execute::stake(deps, info),
+        ExecuteMsg::Unbond { amount } => execute::unbond(deps, env, info, amount),
+        ExecuteMsg::ClaimUnbonded {} => execute::claim_unbonded(deps, env, info),
+        ExecuteMsg::Delegate { operator } => execute::delegate(deps, info, operator),
+        ExecuteMsg::Undelegate { operator, amount } => {
+            execute::undelegate(deps, env, info, operator, amount)
         }
+        ExecuteMsg::SetCommission { rate } => execute::set_commission(deps, info, rate),
+        ExecuteMsg::FundRewards {} => execute::fund_rewards(deps, info),
+        ExecuteMsg::ClaimRewards {} => execute::claim_rewards(deps, info),
+        ExecuteMsg::Reweight { limit } => execute::reweight(deps, limit),
+        ExecuteMsg::RegisterOperator { pubkey, key_type } => {
+            execute::register_operator(deps, env, info, pubkey, key_type)
+        }
+        ExecuteMsg::SubmitEvidence {
+            operator,
+            vote_a,
+            vote_b,
+        } => execute::submit_evidence(deps, env, info, operator, vote_a, vote_b),
+        ExecuteMsg::SubmitRoot {
+            chain_id,
+            root,
+            height,
+        } => execute::submit_root(deps, env, info, chain_id, root, height),
+        ExecuteMsg::RegisterChain {
+            chain_id,
+            family,
+            caip2,
+            hash_function,
+            root_kind,
+            retention,
+        } => execute::register_chain(
+            deps,
+            info,
+            chain_id,
+            family,
+            caip2,
+            hash_function,
+            root_kind,
+            retention,
+        ),
+        ExecuteMsg::SetChainPaused { chain_id, paused } => {
+            execute::set_chain_paused(deps, info, chain_id, paused)
+        }
+        ExecuteMsg::DeregisterChain { chain_id } => execute::deregister_chain(deps, info, chain_id),
+        ExecuteMsg::UpdateConfig(update) => execute::update_config(deps, info, update),
+        ExecuteMsg::SetChainThreshold {
+            chain_id,
+            threshold,
+        } => execute::set_chain_threshold(deps, info, chain_id, threshold),
+        ExecuteMsg::SetChainStrategy { chain_id, strategy } => {
+            execute::set_chain_strategy(deps, info, chain_id, strategy)
+        }
+        ExecuteMsg::SetChainVoteWindow {
+            chain_id,
+            vote_window,
+        } => execute::set_chain_vote_window(deps, info, chain_id, vote_window),
+        ExecuteMsg::SetChainSubmitters {
+            chain_id,
+            submitters,
+            threshold,
+        } => execute::set_chain_submitters(deps, info, chain_id, submitters, threshold),
+        ExecuteMsg::SetChainRetention {
+            chain_id,
+            retention,
+        } => execute::set_chain_retention(deps, info, chain_id, retention),
     }
 }
 
@@ -63,63 +131,687 @@ pub fn execute(
 ///  * aggregation over the VE light client inputs
 ///  * updating contract state to store agreed upon state updates
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(deps: DepsMut, _: Env, msg: SudoMsg) -> ContractResult<Response> {
-    // Store a map of chain_id to Vec<VoteExtension>
-    // Each chain has its own set of VoteExtension--settled separately
-    let mut data_map: BTreeMap<String, Vec<VoteExtension>> =
-        BTreeMap::<String, Vec<VoteExtension>>::new();
-    for generic_hash_vp in msg.data {
-        let hash_vp: VoteExtension = From::from(generic_hash_vp);
-        println!("hash_vp: {:?}", hash_vp);
-        for (chain_id, _) in hash_vp.vote.roots.iter() {
-            match data_map.get(chain_id) {
-                Some(result) => {
-                    let existing_data: Vec<VoteExtension> = Vec::<VoteExtension>::new();
-                    result.to_vec().push(hash_vp.clone());
-                    data_map.insert(chain_id.clone(), existing_data);
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> ContractResult<Response> {
+    // a paused contract must not fail the block, it just ignores the votes
+    if is_paused(deps.storage)? {
+        return Ok(Response::new().add_attribute("paused", "true"));
+    }
+    release_jailed(deps.storage, env.block.time)?;
+    let config = CONFIG.load(deps.storage)?;
+    let DecodedVotes {
+        by_chain,
+        malformed_events,
+        malformed_power,
+        duplicate_operators,
+        received_power,
+        signers,
+        price_votes,
+    } = decode_votes(deps.as_ref(), &env, &config, msg.data)?;
+
+    let total_power = bonded_power(
+        deps.as_ref(),
+        &env,
+        &config,
+        msg.total_power,
+        received_power,
+    )?;
+
+    // aggregate over all the collected vote data
+    let mut vote_roots: Vec<(String, FinalizedRoot, Option<Attestation>)> = Vec::new();
+    let mut quorum_events: Vec<Event> = Vec::new();
+    // power each operator voted for a finalized root with, which rewards are paid by
+    let mut winning_power: BTreeMap<Addr, Uint128> = BTreeMap::new();
+    // for every finalized root, whether each operator voted for it or another root
+    let mut finalized_votes: Vec<BTreeMap<Addr, bool>> = Vec::new();
+    let mut skipped_chains: Vec<String> = Vec::new();
+    let mut ignored_chains: Vec<String> = Vec::new();
+    for (chain_id, vote_extensions) in by_chain.into_iter() {
+        let aggregation = match aggregate_chain(
+            deps.storage,
+            &config,
+            env.block.height,
+            &chain_id,
+            &vote_extensions,
+            total_power,
+        ) {
+            Ok(Some(aggregation)) => aggregation,
+            Ok(None) => {
+                ignored_chains.push(chain_id);
+                continue;
+            }
+            // a single chain with an unrepresentable tally must not stall every other chain
+            Err(ContractError::TallyOverflow { chain_id }) => {
+                skipped_chains.push(chain_id);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let ChainAggregation {
+            votes,
+            window,
+            finalized,
+            tally,
+        } = aggregation;
+        match finalized {
+            Some((source_root, power)) => {
+                clear_pending_votes(deps.storage, &chain_id)?;
+                let mut agreement: BTreeMap<Addr, bool> = BTreeMap::new();
+                let mut voters: Vec<Voter> = vec![];
+                for ve in &votes {
+                    let agrees = ve.vote.roots.get(&chain_id) == Some(&source_root);
+                    if let (Some(operator), true) = (&ve.operator, agrees) {
+                        voters.push(Voter {
+                            operator: operator.clone(),
+                            power: ve.ve_power,
+                        });
+                    }
+                    let Some(Ok(operator)) =
+                        ve.operator.as_deref().map(|o| deps.api.addr_validate(o))
+                    else {
+                        continue;
+                    };
+                    agreement.insert(operator.clone(), agrees);
+                    if agrees {
+                        let operator_power = winning_power.entry(operator).or_default();
+                        // saturating is safe here, it only skews the split of the rewards
+                        *operator_power = operator_power.saturating_add(ve.ve_power);
+                    }
+                }
+                finalized_votes.push(agreement);
+                let attestation = Attestation {
+                    voters,
+                    power_for: power,
+                    power_against: tally.participating_power - power,
+                    total_power: tally.validator_set_power(),
+                    competing_roots: tally
+                        .ranked()
+                        .into_iter()
+                        .filter(|competing| competing.root != source_root)
+                        .collect(),
+                };
+                vote_roots.push((
+                    chain_id,
+                    FinalizedRoot {
+                        root: source_root.root,
+                        source_height: source_root.height,
+                        finalized_height: env.block.height,
+                        finalized_at: env.block.time,
+                        power,
+                    },
+                    Some(attestation),
+                ));
+            }
+            None => {
+                let mut event =
+                    Event::new("quorum_not_reached").add_attribute("chain_id", &chain_id);
+                if let Some((root, power)) = tally.leader() {
+                    event = event
+                        .add_attribute("leading_root", hex::encode(&root.root))
+                        .add_attribute("leading_power", power);
                 }
-                None => {
-                    let new_data: Vec<VoteExtension> = vec![hash_vp.clone()];
-                    data_map.insert(chain_id.clone(), new_data);
+                quorum_events.push(event.add_attributes(participation_attributes(
+                    tally.participating_power,
+                    tally.validator_set_power(),
+                )));
+                prune_pending_votes(deps.storage, &chain_id, window, env.block.height)?;
+                if window > 1 {
+                    save_pending_votes(
+                        deps.storage,
+                        env.block.height,
+                        &chain_id,
+                        &vote_extensions,
+                    )?;
                 }
             }
         }
     }
+    let updated_prices = prices::update(
+        deps.storage,
+        &env.block,
+        price_votes,
+        total_power,
+        config.default_threshold,
+    )?;
+    let distributed = rewards::distribute(deps.storage, &config, winning_power)?;
+    let offenders = liveness::track(
+        deps.storage,
+        &config.liveness,
+        &env.block,
+        &signers,
+        &finalized_votes,
+    )?;
+    let mut burned: Vec<Coin> = vec![];
+    let mut jail_events: Vec<Event> = vec![];
+    let jailed_until = env.block.time.plus_seconds(config.liveness.jail_duration);
+    for operator in offenders {
+        let slashed = execute::slash(
+            deps.storage,
+            &config,
+            &operator,
+            config.liveness.slash_fraction,
+        )?;
+        burned = add_coins(burned, &slashed)?;
+        execute::jail(deps.storage, &operator, jailed_until)?;
+        jail_events.push(
+            Event::new("operator_jailed")
+                .add_attribute("operator", operator)
+                .add_attribute("reason", "downtime")
+                .add_attribute("jailed_until", jailed_until.to_string()),
+        );
+    }
 
-    // aggregate over all the collected vote data
-    let mut vote_roots: Vec<(String, Binary)> = Vec::new();
-    for (chain_id, vote_extensions) in data_map.iter() {
-        if let Some(root) = aggregate_ves(chain_id.clone(), vote_extensions.to_vec()) {
-            vote_roots.push((chain_id.clone(), root));
+    let mut res = write_merkle_roots(deps, &env.block, vote_roots)?.add_events(quorum_events);
+    if !distributed.is_zero() {
+        res = res.add_attribute("rewards_distributed", distributed);
+    }
+    if !updated_prices.is_empty() {
+        res = res.add_attribute("prices_updated", updated_prices.len().to_string());
+    }
+    if !burned.is_empty() {
+        res = res.add_message(BankMsg::Burn { amount: burned });
+    }
+    res = res.add_events(jail_events);
+    if !malformed_events.is_empty() {
+        res = res
+            .add_attribute(
+                "malformed_vote_extensions",
+                malformed_events.len().to_string(),
+            )
+            .add_attribute("malformed_power", malformed_power)
+            .add_events(malformed_events);
+    }
+    res = duplicate_operators.into_iter().fold(res, |res, operator| {
+        res.add_attribute("duplicate_operator", operator)
+    });
+    res = ignored_chains.into_iter().fold(res, |res, chain_id| {
+        res.add_attribute("ignored_chain", chain_id)
+    });
+    Ok(skipped_chains.into_iter().fold(res, |res, chain_id| {
+        res.add_attribute("tally_overflow", chain_id)
+    }))
+}
+
+/// DecodedVotes are the vote extensions of a block, decoded and weighed with the power
+/// of their operators.
+struct DecodedVotes {
+    /// Votes by every chain they include a root for. Each chain is settled separately.
+    by_chain: BTreeMap<String, Vec<VoteExtension>>,
+    malformed_events: Vec<Event>,
+    malformed_power: Uint128,
+    /// Operators that submitted more than one vote extension, only the first counts.
+    duplicate_operators: Vec<String>,
+    /// Power of every decoded vote extension, whether or not it voted for a given chain.
+    received_power: Uint128,
+    /// Operators that submitted a decodable vote extension.
+    signers: BTreeSet<Addr>,
+    /// (price, power) of every price reported for each currency pair.
+    price_votes: BTreeMap<String, Vec<(Uint128, Uint128)>>,
+}
+
+/// decode_votes decodes the vote extensions of a block and weighs every vote with the
+/// power of its operator. An undecodable vote extension is skipped and reported rather
+/// than failing the whole call, so a single faulty validator cannot stall every chain.
+fn decode_votes(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    data: Vec<GenericVE>,
+) -> ContractResult<DecodedVotes> {
+    let mut decoded = DecodedVotes {
+        by_chain: BTreeMap::new(),
+        malformed_events: vec![],
+        malformed_power: Uint128::zero(),
+        duplicate_operators: vec![],
+        received_power: Uint128::zero(),
+        signers: BTreeSet::new(),
+        price_votes: BTreeMap::new(),
+    };
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    for generic_hash_vp in data.into_iter() {
+        // repeats would count the power of the operator more than once
+        if let Some(operator) = &generic_hash_vp.operator {
+            if !seen.insert(operator.clone()) {
+                decoded.duplicate_operators.push(operator.clone());
+                continue;
+            }
+        }
+        let operator = generic_hash_vp.operator.as_deref();
+        let ve_power = match config.power_source {
+            _ if is_jailed(deps, env, operator)? => Uint128::zero(),
+            PowerSource::VotePower => Uint128::from(generic_hash_vp.ve_power),
+            PowerSource::Stake => stake_power(deps, config, operator)?,
+        };
+        let operator = generic_hash_vp.operator.clone();
+        let mut hash_vp = match VoteExtension::try_from(generic_hash_vp) {
+            Ok(hash_vp) => hash_vp,
+            Err(err) => {
+                decoded.malformed_power = decoded.malformed_power.saturating_add(ve_power);
+                decoded.malformed_events.push(
+                    Event::new("malformed_vote_extension")
+                        .add_attribute("operator", operator.unwrap_or_default())
+                        .add_attribute("power", ve_power)
+                        .add_attribute("error", err.to_string()),
+                );
+                continue;
+            }
+        };
+        hash_vp.ve_power = ve_power;
+        // saturating is safe here, an inflated total only makes quorum harder to reach
+        decoded.received_power = decoded.received_power.saturating_add(ve_power);
+        if let Some(Ok(operator)) = hash_vp
+            .operator
+            .as_deref()
+            .map(|o| deps.api.addr_validate(o))
+        {
+            decoded.signers.insert(operator);
+        }
+        for (pair, price) in hash_vp.vote.prices.iter() {
+            if !price.is_zero() {
+                decoded
+                    .price_votes
+                    .entry(pair.clone())
+                    .or_default()
+                    .push((*price, ve_power));
+            }
+        }
+        for (chain_id, _) in hash_vp.vote.roots.iter() {
+            decoded
+                .by_chain
+                .entry(chain_id.clone())
+                .or_default()
+                .push(hash_vp.clone());
         }
     }
-    write_merkle_roots(deps, vote_roots)
+    Ok(decoded)
 }
 
-fn aggregate_ves(chain_id: String, votes: Vec<VoteExtension>) -> Option<Binary> {
-    // aggregate over all the collected vote data
-    let mut hashes_to_vp: BTreeMap<Binary, u64> = BTreeMap::new();
-    let mut max_power: u64 = 0;
-    let mut total_power: u64 = 0;
-    let mut best_hash: Option<Binary> = None;
-    for ve in votes {
-        let voted_root = ve.vote.roots.get(&chain_id)?;
-        let mut existing_power: u64 = 0;
-        if let Some(power) = hashes_to_vp.get(voted_root) {
-            existing_power = *power;
+/// ChainAggregation is the outcome of aggregating the votes of a block for one chain.
+struct ChainAggregation {
+    /// Votes counted, including the ones carried over from earlier blocks.
+    votes: Vec<VoteExtension>,
+    /// Number of blocks votes count for. With more than one, votes carry over to later
+    /// blocks until a root is finalized.
+    window: u64,
+    /// Root that is finalized, if any, with the power that voted for it.
+    finalized: Option<(SourceRoot, Uint128)>,
+    tally: Tally,
+}
+
+/// aggregate_chain aggregates the votes cast for `chain_id` at `height` together with
+/// the votes carried over for it, with the threshold and strategy of the chain. Votes
+/// for chains that are not registered or are paused do not count and return None.
+fn aggregate_chain(
+    storage: &dyn Storage,
+    config: &Config,
+    height: u64,
+    chain_id: &str,
+    vote_extensions: &[VoteExtension],
+    total_power: Uint128,
+) -> ContractResult<Option<ChainAggregation>> {
+    if CHAINS
+        .may_load(storage, chain_id.to_string())?
+        .map_or(true, |chain| chain.paused)
+    {
+        return Ok(None);
+    }
+    let chain_config = CHAIN_CONFIGS
+        .may_load(storage, chain_id.to_string())?
+        .unwrap_or_default();
+    let threshold = chain_config.threshold.unwrap_or(config.default_threshold);
+    let strategy = chain_config
+        .strategy
+        .clone()
+        .unwrap_or_else(|| config.default_strategy.clone());
+    let window = vote_window(config, &chain_config, &strategy);
+    // votes cast in this block replace the carried over vote of the same operator
+    let mut votes = vote_extensions.to_vec();
+    if window > 1 {
+        let pending = pending_votes(storage, chain_id, window, height)?;
+        votes.extend(pending.into_iter().filter(|pending_vote| {
+            !vote_extensions
+                .iter()
+                .any(|ve| ve.operator.is_some() && ve.operator == pending_vote.operator)
+        }));
+    }
+    let (finalized, tally) = aggregate_ves(
+        chain_id.to_string(),
+        &votes,
+        total_power,
+        strategy.aggregation_strategy(),
+        threshold,
+    )?;
+    Ok(Some(ChainAggregation {
+        votes,
+        window,
+        finalized,
+        tally,
+    }))
+}
+
+/// stake_power computes the voting power of an operator from the coins it has staked
+/// with the contract and the coins delegated to it, weighted per denom by the
+/// configured `denom_weights`. Vote extensions without a registered operator carry no
+/// power.
+fn stake_power(deps: Deps, config: &Config, operator: Option<&str>) -> ContractResult<Uint128> {
+    let operator = match operator.map(|operator| deps.api.addr_validate(operator)) {
+        Some(Ok(operator)) if OPERATORS.has(deps.storage, operator.clone()) => operator,
+        _ => return Ok(Uint128::zero()),
+    };
+    let overflow = || ContractError::VotePowerOverflow {
+        operator: operator.to_string(),
+    };
+    let staked_coins = STAKE_MAP
+        .may_load(deps.storage, operator.clone())?
+        .unwrap_or_default();
+    let delegated_coins = DELEGATED
+        .may_load(deps.storage, operator.clone())?
+        .unwrap_or_default();
+    let bonded_coins = add_coins(staked_coins, &delegated_coins).map_err(|_| overflow())?;
+    weighted_power(&bonded_coins, &config.denom_weights).ok_or_else(overflow)
+}
+
+/// slash_factors loads the slash factor of `operator` along with the one it had when
+/// the delegation and undelegations of `delegator` were last updated.
+fn slash_factors(
+    storage: &dyn Storage,
+    operator: &Addr,
+    delegator: &Addr,
+) -> StdResult<(SlashFactor, SlashFactor)> {
+    let slash_factor = SLASH_FACTORS
+        .may_load(storage, operator.clone())?
+        .unwrap_or_default();
+    let recorded = DELEGATION_SLASH_FACTORS
+        .may_load(storage, (operator.clone(), delegator.clone()))?
+        .unwrap_or_default();
+    Ok((slash_factor, recorded))
+}
+
+/// delegation loads the delegation of `delegator` to `operator`, without the slashes
+/// of the operator since it was last updated.
+fn delegation(
+    storage: &dyn Storage,
+    operator: &Addr,
+    delegator: &Addr,
+) -> StdResult<Option<Vec<Coin>>> {
+    let Some(amount) = DELEGATIONS.may_load(storage, (operator.clone(), delegator.clone()))? else {
+        return Ok(None);
+    };
+    let (slash_factor, recorded) = slash_factors(storage, operator, delegator)?;
+    Ok(Some(slash_factor.scale(&recorded, amount)))
+}
+
+/// undelegations loads the undelegations of `delegator` from `operator`, without the
+/// slashes of the operator since they were last updated.
+fn undelegations(
+    storage: &dyn Storage,
+    operator: &Addr,
+    delegator: &Addr,
+) -> StdResult<Option<Vec<Unbonding>>> {
+    let Some(queue) = UNDELEGATIONS.may_load(storage, (operator.clone(), delegator.clone()))?
+    else {
+        return Ok(None);
+    };
+    let (slash_factor, recorded) = slash_factors(storage, operator, delegator)?;
+    Ok(Some(
+        queue
+            .into_iter()
+            .filter_map(|unbonding| {
+                let amount = slash_factor.scale(&recorded, unbonding.amount);
+                (!amount.is_empty()).then_some(Unbonding {
+                    amount,
+                    release_at: unbonding.release_at,
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// operator_stake returns the self-stake of `operator` along with the coins delegated
+/// to it.
+fn operator_stake(storage: &dyn Storage, operator: &Addr) -> StdResult<Vec<Coin>> {
+    let staked_coins = STAKE_MAP
+        .may_load(storage, operator.clone())?
+        .unwrap_or_default();
+    let delegated_coins = DELEGATED
+        .may_load(storage, operator.clone())?
+        .unwrap_or_default();
+    Ok(add_coins(staked_coins, &delegated_coins)?)
+}
+
+/// is_bonded checks whether the stake of `operator` counts towards `BONDED`, which it
+/// does while the operator is registered and has no jail term that was not released
+/// yet.
+fn is_bonded(storage: &dyn Storage, operator: &Addr) -> StdResult<bool> {
+    if !OPERATORS.has(storage, operator.clone()) {
+        return Ok(false);
+    }
+    let Some(jailed_until) = JAILED.may_load(storage, operator.clone())? else {
+        return Ok(true);
+    };
+    Ok(!JAIL_RELEASES.has(storage, (jailed_until.nanos(), operator.clone())))
+}
+
+/// due_releases returns the jail terms that ended by `time` but were not released yet.
+fn due_releases(storage: &dyn Storage, time: Timestamp) -> StdResult<Vec<(u64, Addr)>> {
+    JAIL_RELEASES
+        .prefix_range(
+            storage,
+            None,
+            Some(PrefixBound::inclusive(time.nanos())),
+            Order::Ascending,
+        )
+        .map(|item| item.map(|(key, _)| key))
+        .collect()
+}
+
+/// bonded_stake returns the stake the bonded power is made of at `time`, which
+/// includes the operators whose jail term ended but was not released yet.
+fn bonded_stake(storage: &dyn Storage, time: Timestamp) -> StdResult<Vec<Coin>> {
+    let mut bonded = BONDED.may_load(storage)?.unwrap_or_default();
+    for (_, operator) in due_releases(storage, time)? {
+        if OPERATORS.has(storage, operator.clone()) {
+            bonded = add_coins(bonded, &operator_stake(storage, &operator)?)?;
         }
-        total_power += ve.ve_power;
-        let new_hash_power = existing_power + ve.ve_power;
-        if new_hash_power > max_power {
-            max_power = new_hash_power;
-            best_hash = Some(voted_root.clone());
+    }
+    Ok(bonded)
+}
+
+/// release_jailed bonds the stake of the operators whose jail term ended by `time`
+/// again.
+fn release_jailed(storage: &mut dyn Storage, time: Timestamp) -> StdResult<()> {
+    let bonded = bonded_stake(storage, time)?;
+    for key in due_releases(storage, time)? {
+        JAIL_RELEASES.remove(storage, key);
+    }
+    BONDED.save(storage, &bonded)
+}
+
+/// is_jailed checks whether `operator` is serving a jail term at the current block.
+fn is_jailed(deps: Deps, env: &Env, operator: Option<&str>) -> StdResult<bool> {
+    let Some(Ok(operator)) = operator.map(|operator| deps.api.addr_validate(operator)) else {
+        return Ok(false);
+    };
+    Ok(JAILED
+        .may_load(deps.storage, operator)?
+        .is_some_and(|jailed_until| jailed_until > env.block.time))
+}
+
+/// bonded_power returns the power of the full validator set. With stake based power
+/// it is the weighted stake of the registered operators that are not jailed, the only
+/// stakers that can vote with any power, otherwise the power reported by the module.
+/// It is never less than the power of the vote extensions actually received.
+fn bonded_power(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    reported_power: Option<u64>,
+    received_power: Uint128,
+) -> StdResult<Uint128> {
+    let bonded_power = match config.power_source {
+        PowerSource::Stake => {
+            let bonded = bonded_stake(deps.storage, env.block.time)?;
+            // saturating is safe here, an inflated total only makes quorum harder to reach
+            weighted_power(&bonded, &config.denom_weights).unwrap_or(Uint128::MAX)
         }
-        hashes_to_vp.insert(voted_root.clone(), new_hash_power);
+        PowerSource::VotePower => reported_power.map(Uint128::from).unwrap_or_default(),
+    };
+    Ok(bonded_power.max(received_power))
+}
+
+/// vote_window returns for how many blocks a vote for the chain keeps counting.
+/// `FirstToQuorum` always accumulates over its own window.
+fn vote_window(config: &Config, chain_config: &ChainConfig, strategy: &Strategy) -> u64 {
+    match strategy {
+        Strategy::FirstToQuorum { window } => *window,
+        _ => chain_config
+            .vote_window
+            .unwrap_or(config.default_vote_window),
+    }
+}
+
+/// prune_pending_votes removes the votes carried over for a chain that were cast a
+/// full vote window or more blocks before `height`.
+fn prune_pending_votes(
+    storage: &mut dyn Storage,
+    chain_id: &str,
+    window: u64,
+    height: u64,
+) -> StdResult<()> {
+    let expired: Vec<String> = PENDING_VOTES
+        .prefix(chain_id.to_string())
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, vote)| {
+                vote.height.saturating_add(window) <= height
+            })
+        })
+        .map(|item| item.map(|(operator, _)| operator))
+        .collect::<StdResult<_>>()?;
+    for operator in expired {
+        remove_pending_vote(storage, chain_id.to_string(), operator);
+    }
+    Ok(())
+}
+
+/// pending_votes loads the votes carried over for a chain from earlier blocks that still
+/// count at `height`.
+fn pending_votes(
+    storage: &dyn Storage,
+    chain_id: &str,
+    window: u64,
+    height: u64,
+) -> StdResult<Vec<VoteExtension>> {
+    PENDING_VOTES
+        .prefix(chain_id.to_string())
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| {
+            // votes cast a full window ago no longer count, whether or not pruned yet
+            item.as_ref().map_or(true, |(_, vote)| {
+                vote.height.saturating_add(window) > height
+            })
+        })
+        .map(|item| {
+            let (operator, pending_vote) = item?;
+            Ok(VoteExtension {
+                vote: Vote {
+                    roots: BTreeMap::from([(chain_id.to_string(), pending_vote.root)]),
+                    prices: BTreeMap::new(),
+                },
+                ve_power: pending_vote.power,
+                operator: Some(operator),
+            })
+        })
+        .collect()
+}
+
+/// save_pending_votes records the latest vote of every identified operator so it keeps
+/// counting in later blocks. Anonymous votes cannot be deduplicated across blocks and
+/// only count in the block they were cast in.
+fn save_pending_votes(
+    storage: &mut dyn Storage,
+    height: u64,
+    chain_id: &str,
+    votes: &[VoteExtension],
+) -> StdResult<()> {
+    for ve in votes {
+        let (Some(operator), Some(root)) = (&ve.operator, ve.vote.roots.get(chain_id)) else {
+            continue;
+        };
+        PENDING_VOTES.save(
+            storage,
+            (chain_id.to_string(), operator.clone()),
+            &PendingVote {
+                root: root.clone(),
+                power: ve.ve_power,
+                height,
+            },
+        )?;
+        OPERATOR_PENDING_VOTES.save(
+            storage,
+            (operator.clone(), chain_id.to_string()),
+            &Empty {},
+        )?;
     }
-    if (max_power as f64) / (total_power as f64) >= QUARUM {
-        return best_hash.clone();
+    Ok(())
+}
+
+/// remove_pending_vote removes the pending vote of `operator` for a chain along with
+/// its index entry.
+fn remove_pending_vote(storage: &mut dyn Storage, chain_id: String, operator: String) {
+    OPERATOR_PENDING_VOTES.remove(storage, (operator.clone(), chain_id.clone()));
+    PENDING_VOTES.remove(storage, (chain_id, operator));
+}
+
+fn clear_pending_votes(storage: &mut dyn Storage, chain_id: &str) -> StdResult<()> {
+    let operators: Vec<String> = PENDING_VOTES
+        .prefix(chain_id.to_string())
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for operator in operators {
+        remove_pending_vote(storage, chain_id.to_string(), operator);
+    }
+    Ok(())
+}
+
+/// aggregate_ves tallies the power behind every root voted for `chain_id` and lets
+/// `strategy` pick the root to finalize, if any, along with the power that voted for it.
+/// The tally is returned either way. `total_power` is the power of the full validator
+/// set. All arithmetic is integer based and checked, so an overflowing tally is
+/// reported as an error instead of aborting the block.
+fn aggregate_ves(
+    chain_id: String,
+    votes: &[VoteExtension],
+    total_power: Uint128,
+    strategy: &dyn AggregationStrategy,
+    threshold: Decimal,
+) -> ContractResult<(Option<(SourceRoot, Uint128)>, Tally)> {
+    let mut tally = Tally::new(total_power);
+    for ve in votes {
+        let Some(voted_root) = ve.vote.roots.get(&chain_id) else {
+            continue;
+        };
+        tally
+            .add_vote(voted_root.clone(), ve.ve_power)
+            .map_err(|_| ContractError::TallyOverflow {
+                chain_id: chain_id.clone(),
+            })?;
     }
-    None
+    let finalized = strategy.aggregate(&tally, threshold).map(|root| {
+        let power = tally.roots[&root];
+        (root, power)
+    });
+    Ok((finalized, tally))
+}
+
+/// participation_attributes describe how much of the validator set took part in a vote.
+fn participation_attributes(participating_power: Uint128, total_power: Uint128) -> Vec<Attribute> {
+    let participation =
+        Decimal::checked_from_ratio(participating_power, total_power).unwrap_or_default();
+    vec![
+        Attribute::new("participating_power", participating_power),
+        Attribute::new("total_power", total_power),
+        Attribute::new("participation", participation.to_string()),
+    ]
 }
 
 /// instantiate is used to construct the contract
@@ -128,152 +820,5017 @@ pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let owner = match &msg.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => info.sender,
+    };
+    initialize(deps, owner.clone(), msg)?;
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("owner", info.sender))
+        .add_attribute("owner", owner))
+}
+
+/// migrate upgrades the state written by any earlier version of this contract by
+/// running, in order, every migration step newer than the stored version.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> ContractResult<Response> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract {
+            contract: stored.contract,
+        });
+    }
+    let from = parse_version(&stored.version)?;
+    let to = parse_version(CONTRACT_VERSION)?;
+    if from > to {
+        return Err(ContractError::MigrationDowngrade {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+
+    for (version, step) in MIGRATIONS {
+        let version = parse_version(version)?;
+        if from < version && version <= to {
+            step(deps.branch(), &env, &from, &msg)?;
+        }
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", from.to_string())
+        .add_attribute("to_version", to.to_string()))
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    version
+        .parse()
+        .map_err(|err: semver::Error| StdError::parse_err("Version", err.to_string()))
+}
+
+/// initialize stores the ownership and configuration of a freshly set up contract.
+pub(crate) fn initialize(deps: DepsMut, owner: Addr, msg: InstantiateMsg) -> ContractResult<()> {
+    validate_threshold(msg.default_threshold)?;
+    OWNERSHIP.save(
+        deps.storage,
+        &Ownership {
+            owner: Some(owner),
+            pending_owner: None,
+        },
+    )?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            unbonding_period: msg.unbonding_period,
+            power_source: msg.power_source,
+            denom_weights: msg.denom_weights,
+            default_threshold: msg.default_threshold,
+            default_strategy: Strategy::default(),
+            default_vote_window: 1,
+            default_retention: Retention {
+                max_roots: CACHE_SIZE,
+                max_age_seconds: None,
+                max_age_blocks: None,
+            },
+            slashing: SlashingConfig {
+                slash_fraction: Decimal::percent(5),
+                reporter_bounty: Decimal::percent(10),
+                jail_duration: 24 * 60 * 60,
+            },
+            key_rotation_delay: 24 * 60 * 60,
+            rewards: None,
+            liveness: LivenessConfig {
+                window: 100,
+                min_signed: Decimal::percent(50),
+                slash_fraction: Decimal::percent(1),
+                jail_duration: 10 * 60,
+            },
+            record_attestations: false,
+        },
+    )?;
+    Ok(())
+}
+
+fn validate_threshold(threshold: Decimal) -> ContractResult<()> {
+    if threshold.is_zero() || threshold > Decimal::one() {
+        return Err(ContractError::InvalidThreshold {});
+    }
+    Ok(())
+}
+
+fn validate_strategy(strategy: &Strategy) -> ContractResult<()> {
+    if let Strategy::FirstToQuorum { window } = strategy {
+        validate_vote_window(*window)?;
+    }
+    Ok(())
+}
+
+fn validate_vote_window(window: u64) -> ContractResult<()> {
+    if window == 0 {
+        return Err(ContractError::InvalidVoteWindow {});
+    }
+    Ok(())
+}
+
+fn validate_slashing(slashing: &SlashingConfig) -> ContractResult<()> {
+    if slashing.slash_fraction > Decimal::one() || slashing.reporter_bounty > Decimal::one() {
+        return Err(ContractError::InvalidSlashing {});
+    }
+    Ok(())
+}
+
+fn validate_liveness(liveness: &LivenessConfig) -> ContractResult<()> {
+    if liveness.window == 0
+        || liveness.min_signed > Decimal::one()
+        || liveness.slash_fraction > Decimal::one()
+    {
+        return Err(ContractError::InvalidLiveness {});
+    }
+    Ok(())
+}
+
+fn validate_retention(retention: &Retention) -> ContractResult<()> {
+    if retention.max_roots == 0
+        || retention.max_age_seconds == Some(0)
+        || retention.max_age_blocks == Some(0)
+    {
+        return Err(ContractError::InvalidRetention {});
+    }
+    Ok(())
+}
+
+/// chain_retention returns the retention of a chain, falling back to the default.
+fn chain_retention(storage: &dyn Storage, config: &Config, chain_id: &str) -> StdResult<Retention> {
+    Ok(CHAIN_CONFIGS
+        .may_load(storage, chain_id.to_string())?
+        .and_then(|chain_config| chain_config.retention)
+        .unwrap_or_else(|| config.default_retention.clone()))
+}
+
+/// root_expired checks whether a root that is `age` roots old has exceeded any limit
+/// of `retention` at `block`.
+fn root_expired(retention: &Retention, root: &FinalizedRoot, age: u64, block: &BlockInfo) -> bool {
+    let expired_by_age = |max_age: Option<u64>, since: u64, now: u64| {
+        max_age.is_some_and(|max_age| now.saturating_sub(since) > max_age)
+    };
+    age > retention.max_roots
+        || expired_by_age(
+            retention.max_age_seconds,
+            root.finalized_at.seconds(),
+            block.time.seconds(),
+        )
+        || expired_by_age(
+            retention.max_age_blocks,
+            root.finalized_height,
+            block.height,
+        )
+}
+
+/// validate_caip2 checks that `caip2` is a `namespace:reference` CAIP-2 chain id.
+fn validate_caip2(caip2: &str) -> ContractResult<()> {
+    let valid = caip2.split_once(':').is_some_and(|(namespace, reference)| {
+        (3..=8).contains(&namespace.len())
+            && namespace
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+            && (1..=32).contains(&reference.len())
+            && reference
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    });
+    if !valid {
+        return Err(ContractError::InvalidCaip2 {
+            caip2: caip2.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn assert_registered(deps: Deps, chain_id: &str) -> ContractResult<ChainInfo> {
+    CHAINS
+        .may_load(deps.storage, chain_id.to_string())?
+        .ok_or_else(|| ContractError::ChainNotRegistered {
+            chain_id: chain_id.to_string(),
+        })
+}
+
+fn assert_admin(deps: Deps, info: &MessageInfo) -> ContractResult<()> {
+    if OWNERSHIP.load(deps.storage)?.owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn is_paused(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(PAUSED.may_load(storage)?.unwrap_or_default())
 }
 
 pub mod execute {
     use super::*;
-    use crate::state::ChainHashes;
+    use crate::helpers::{split_coins, sub_coins, validate_operator_key, verify_signature};
+    use crate::msg::{ConfigUpdate, SignedVote};
+    use crate::state::{
+        ChainConfig, ChainFamily, HashFunction, KeyType, OperatorInfo, OperatorKey, PendingKey,
+        Reweight, RootCursor, RootKind, SubmitterSet, DELEGATOR_OPERATORS, EVIDENCE, REWARD_POOL,
+        REWEIGHT, ROOT_SUBMISSIONS, SUBMITTERS, TOTAL_STAKE, UNBONDING_QUEUE, UNDELEGATED,
+    };
+    use cosmwasm_std::Timestamp;
 
-    /// write_merkle_roots implements the state update method of the contract.
-    /// Merkle roots are input using a map of chain_id to hash value.
-    /// If a chain has reached the maximum cache size, it evicts the oldest entry and
-    /// inserts a new one.
-    /// Otherwise, it writes a new vector to state for the chain.
-    pub fn write_merkle_roots(
+    pub fn transfer_ownership(
         deps: DepsMut,
-        merkle_roots: Vec<(String, Binary)>,
-    ) -> Result<Response, ContractError> {
-        for (chain_id, merkle_hash) in merkle_roots.iter() {
-            // Get the existing vector of merkle roots for the chain_id
-            // let mut root_set: Vec<Binary>;
-            let mut root_set: ChainHashes;
-            if MERKLE_ROOTS.has(deps.storage, chain_id.to_string()) {
-                root_set = MERKLE_ROOTS.load(deps.storage, chain_id.clone()).unwrap();
-                let mut seen = false;
-                for root in root_set.hashes.iter() {
-                    if root.eq(merkle_hash) {
-                        seen = true;
-                    }
-                }
-                if seen {
-                    continue;
-                }
-                if root_set.hashes.len() == root_set.max_size {
-                    root_set.hashes.remove(0);
-                }
-                root_set.hashes.push(merkle_hash.clone());
-            } else {
-                root_set = ChainHashes {
-                    chain_id: chain_id.clone(),
-                    hashes: Vec::new(),
-                    max_size: CACHE_SIZE,
-                };
-                root_set.hashes.push(merkle_hash.clone());
+        info: MessageInfo,
+        new_owner: String,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        let new_owner = deps.api.addr_validate(&new_owner)?;
+        OWNERSHIP.update(deps.storage, |mut ownership| -> StdResult<_> {
+            ownership.pending_owner = Some(new_owner.clone());
+            Ok(ownership)
+        })?;
+
+        Ok(Response::new()
+            .add_attribute("action", "transfer_ownership")
+            .add_attribute("pending_owner", new_owner))
+    }
+
+    pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> ContractResult<Response> {
+        let mut ownership = OWNERSHIP.load(deps.storage)?;
+        match &ownership.pending_owner {
+            None => return Err(ContractError::NoPendingOwner {}),
+            Some(pending_owner) if *pending_owner != info.sender => {
+                return Err(ContractError::Unauthorized {})
             }
-            MERKLE_ROOTS.save(deps.storage, chain_id.clone(), &root_set)?;
+            Some(_) => {}
+        }
+        ownership.owner = ownership.pending_owner.take();
+        OWNERSHIP.save(deps.storage, &ownership)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "accept_ownership")
+            .add_attribute("owner", info.sender))
+    }
+
+    pub fn renounce_ownership(deps: DepsMut, info: MessageInfo) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        OWNERSHIP.save(
+            deps.storage,
+            &Ownership {
+                owner: None,
+                pending_owner: None,
+            },
+        )?;
+
+        Ok(Response::new().add_attribute("action", "renounce_ownership"))
+    }
+
+    pub fn set_paused(deps: DepsMut, info: MessageInfo, paused: bool) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        PAUSED.save(deps.storage, &paused)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_paused")
+            .add_attribute("paused", paused.to_string()))
+    }
+
+    pub fn stake(deps: DepsMut, info: MessageInfo) -> ContractResult<Response> {
+        let config = CONFIG.load(deps.storage)?;
+        let staked_coins = STAKE_MAP
+            .may_load(deps.storage, info.sender.clone())?
+            .unwrap_or_default();
+        save_stake(
+            deps.storage,
+            &config,
+            &info.sender,
+            add_coins(staked_coins, &info.funds)?,
+        )?;
+        let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+        TOTAL_STAKE.save(deps.storage, &add_coins(total_stake, &info.funds)?)?;
+        add_bonded(deps.storage, &info.sender, &info.funds)?;
+
+        Ok(Response::new().add_attribute("action", "stake"))
+    }
+
+    /// unbond removes `amount` from the sender's stake and queues it for release
+    /// once the configured unbonding period has elapsed.
+    pub fn unbond(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        amount: Vec<Coin>,
+    ) -> ContractResult<Response> {
+        let amount: Vec<Coin> = amount.into_iter().filter(|c| !c.amount.is_zero()).collect();
+        if amount.is_empty() {
+            return Err(ContractError::EmptyUnbond {});
         }
-        Ok(Response::new())
+
+        let config = CONFIG.load(deps.storage)?;
+        let staked_coins = STAKE_MAP
+            .may_load(deps.storage, info.sender.clone())?
+            .unwrap_or_default();
+        save_stake(
+            deps.storage,
+            &config,
+            &info.sender,
+            sub_coins(staked_coins, &amount)?,
+        )?;
+        let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+        TOTAL_STAKE.save(deps.storage, &sub_coins(total_stake, &amount)?)?;
+        sub_bonded(deps.storage, &info.sender, &amount)?;
+
+        let release_at = env.block.time.plus_seconds(config.unbonding_period);
+        let mut queue = UNBONDING_QUEUE
+            .may_load(deps.storage, info.sender.clone())?
+            .unwrap_or_default();
+        queue.push(Unbonding { amount, release_at });
+        UNBONDING_QUEUE.save(deps.storage, info.sender, &queue)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "unbond")
+            .add_attribute("release_at", release_at.to_string()))
     }
-}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::LookupHash { chain_id, hash } => {
-            to_json_binary(&query::lookup_hash(deps, chain_id, hash)?)
+    /// add_bonded adds `amount` to the bonded stake if the stake of `operator` counts
+    /// towards it.
+    fn add_bonded(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        amount: &[Coin],
+    ) -> ContractResult<()> {
+        if is_bonded(storage, operator)? {
+            let bonded = BONDED.may_load(storage)?.unwrap_or_default();
+            BONDED.save(storage, &add_coins(bonded, amount)?)?;
         }
+        Ok(())
     }
-}
 
-pub mod query {
-    use super::*;
-    use cosmwasm_std::StdError;
+    /// sub_bonded takes `amount` out of the bonded stake if the stake of `operator`
+    /// counts towards it.
+    fn sub_bonded(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        amount: &[Coin],
+    ) -> ContractResult<()> {
+        if is_bonded(storage, operator)? {
+            let bonded = BONDED.may_load(storage)?.unwrap_or_default();
+            BONDED.save(storage, &sub_coins(bonded, amount)?)?;
+        }
+        Ok(())
+    }
 
-    pub fn lookup_hash(
-        deps: Deps,
-        chain_id: String,
-        hash: Binary,
-    ) -> StdResult<LookupHashResponse> {
-        let chain_hashes = MERKLE_ROOTS.load(deps.storage, chain_id)?;
-        for (index, chain_hash) in chain_hashes.hashes.iter().enumerate() {
-            if chain_hash.eq(&hash) {
-                return Ok(LookupHashResponse {
-                    age: (chain_hashes.hashes.len() - index) as u64,
-                });
+    /// save_stake stores the self-stake of `staker`, removing it once empty, and
+    /// updates the shares it earns rewards with.
+    fn save_stake(
+        storage: &mut dyn Storage,
+        config: &Config,
+        staker: &Addr,
+        staked_coins: Vec<Coin>,
+    ) -> ContractResult<()> {
+        if staked_coins.is_empty() {
+            STAKE_MAP.remove(storage, staker.clone());
+        } else {
+            STAKE_MAP.save(storage, staker.clone(), &staked_coins)?;
+        }
+        rewards::update_shares(storage, config, staker, staker, &staked_coins)
+    }
+
+    /// claim_unbonded pays out every unbonding and undelegation entry of the sender
+    /// whose release time has passed.
+    pub fn claim_unbonded(deps: DepsMut, env: Env, info: MessageInfo) -> ContractResult<Response> {
+        let config = CONFIG.load(deps.storage)?;
+        let mut claimed: Vec<Coin> = vec![];
+
+        let queue = UNBONDING_QUEUE
+            .may_load(deps.storage, info.sender.clone())?
+            .unwrap_or_default();
+        let pending = take_matured(queue, env.block.time, &mut claimed)?;
+        if pending.is_empty() {
+            UNBONDING_QUEUE.remove(deps.storage, info.sender.clone());
+        } else {
+            UNBONDING_QUEUE.save(deps.storage, info.sender.clone(), &pending)?;
+        }
+
+        let operators: Vec<Addr> = DELEGATOR_OPERATORS
+            .prefix(info.sender.clone())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        for operator in operators {
+            realize_slashes(deps.storage, &config, &operator, &info.sender)?;
+            let queue = UNDELEGATIONS
+                .may_load(deps.storage, (operator.clone(), info.sender.clone()))?
+                .unwrap_or_default();
+            let mut released: Vec<Coin> = vec![];
+            let pending = take_matured(queue, env.block.time, &mut released)?;
+            save_undelegations(deps.storage, &operator, &info.sender, pending)?;
+            if !released.is_empty() {
+                let undelegated = UNDELEGATED
+                    .may_load(deps.storage, operator.clone())?
+                    .unwrap_or_default();
+                save_undelegated(deps.storage, &operator, sub_coins(undelegated, &released)?)?;
+                claimed = add_coins(claimed, &released)?;
             }
         }
-        Err(StdError::not_found("HashNotFound".to_string()))
+
+        if claimed.is_empty() {
+            return Err(ContractError::NothingToClaim {});
+        }
+
+        Ok(Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: claimed,
+            })
+            .add_attribute("action", "claim_unbonded"))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::msg::{GenericVE, Vote};
-    use bincode::serialize;
-    use cosmwasm_std::coins;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    /// take_matured adds the entries of `queue` released by `time` to `claimed` and
+    /// returns the rest.
+    fn take_matured(
+        queue: Vec<Unbonding>,
+        time: Timestamp,
+        claimed: &mut Vec<Coin>,
+    ) -> ContractResult<Vec<Unbonding>> {
+        let (matured, pending): (Vec<Unbonding>, Vec<Unbonding>) = queue
+            .into_iter()
+            .partition(|unbonding| unbonding.release_at <= time);
+        for unbonding in matured {
+            *claimed = add_coins(std::mem::take(claimed), &unbonding.amount)?;
+        }
+        Ok(pending)
+    }
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    /// delegate bonds the sent funds to a registered operator.
+    pub fn delegate(
+        deps: DepsMut,
+        info: MessageInfo,
+        operator: String,
+    ) -> ContractResult<Response> {
+        let operator = deps.api.addr_validate(&operator)?;
+        // the reward position of a self-delegation would be the one of the self-stake
+        if operator == info.sender {
+            return Err(ContractError::SelfDelegation {});
+        }
+        if !OPERATORS.has(deps.storage, operator.clone()) {
+            return Err(ContractError::OperatorKeyNotRegistered {
+                operator: operator.to_string(),
+            });
+        }
+        let amount: Vec<Coin> = info
+            .funds
+            .into_iter()
+            .filter(|c| !c.amount.is_zero())
+            .collect();
+        if amount.is_empty() {
+            return Err(ContractError::EmptyDelegation {});
+        }
 
-        let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(1000, "earth"));
+        let config = CONFIG.load(deps.storage)?;
+        realize_slashes(deps.storage, &config, &operator, &info.sender)?;
+        let delegation = DELEGATIONS
+            .may_load(deps.storage, (operator.clone(), info.sender.clone()))?
+            .unwrap_or_default();
+        save_delegation(
+            deps.storage,
+            &config,
+            &operator,
+            &info.sender,
+            add_coins(delegation, &amount)?,
+        )?;
+        let delegated = DELEGATED
+            .may_load(deps.storage, operator.clone())?
+            .unwrap_or_default();
+        DELEGATED.save(
+            deps.storage,
+            operator.clone(),
+            &add_coins(delegated, &amount)?,
+        )?;
+        let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+        TOTAL_STAKE.save(deps.storage, &add_coins(total_stake, &amount)?)?;
+        add_bonded(deps.storage, &operator, &amount)?;
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        Ok(Response::new()
+            .add_attribute("action", "delegate")
+            .add_attribute("delegator", info.sender)
+            .add_attribute("operator", operator))
     }
 
-    #[test]
-    fn do_some_hash_stuff() {
-        let mut deps = mock_dependencies();
+    /// undelegate removes `amount` from the sender's delegation to `operator` and
+    /// queues it for release once the unbonding period has elapsed.
+    pub fn undelegate(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        operator: String,
+        amount: Vec<Coin>,
+    ) -> ContractResult<Response> {
+        let operator = deps.api.addr_validate(&operator)?;
+        let amount: Vec<Coin> = amount.into_iter().filter(|c| !c.amount.is_zero()).collect();
+        if amount.is_empty() {
+            return Err(ContractError::EmptyUnbond {});
+        }
 
-        let msg = InstantiateMsg {};
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let config = CONFIG.load(deps.storage)?;
+        realize_slashes(deps.storage, &config, &operator, &info.sender)?;
+        let delegation = DELEGATIONS
+            .may_load(deps.storage, (operator.clone(), info.sender.clone()))?
+            .unwrap_or_default();
+        save_delegation(
+            deps.storage,
+            &config,
+            &operator,
+            &info.sender,
+            sub_coins(delegation, &amount)?,
+        )?;
+        let delegated = DELEGATED
+            .may_load(deps.storage, operator.clone())?
+            .unwrap_or_default();
+        save_delegated(deps.storage, &operator, sub_coins(delegated, &amount)?)?;
+        let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+        TOTAL_STAKE.save(deps.storage, &sub_coins(total_stake, &amount)?)?;
+        sub_bonded(deps.storage, &operator, &amount)?;
+        let undelegated = UNDELEGATED
+            .may_load(deps.storage, operator.clone())?
+            .unwrap_or_default();
+        UNDELEGATED.save(
+            deps.storage,
+            operator.clone(),
+            &add_coins(undelegated, &amount)?,
+        )?;
 
-        let test_case_msg = SudoMsg { data: vec![] };
-        assert!(Ok(Response::new()).eq(&sudo(deps.as_mut(), mock_env(), test_case_msg)));
+        let release_at = env.block.time.plus_seconds(config.unbonding_period);
+        let mut queue = UNDELEGATIONS
+            .may_load(deps.storage, (operator.clone(), info.sender.clone()))?
+            .unwrap_or_default();
+        queue.push(Unbonding { amount, release_at });
+        save_undelegations(deps.storage, &operator, &info.sender, queue)?;
 
-        // let bin = Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg==").unwrap();
+        Ok(Response::new()
+            .add_attribute("action", "undelegate")
+            .add_attribute("delegator", info.sender)
+            .add_attribute("operator", operator)
+            .add_attribute("release_at", release_at.to_string()))
+    }
 
-        let mut map_thing = BTreeMap::<String, Binary>::new();
-        map_thing.insert(
-            "foo".to_string(),
-            Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg").unwrap(),
-        );
-        let vote_ex = Vote {
-            roots: map_thing.clone(),
-        };
-        println!("vote_ex: {:?}", hex::encode(serialize(&vote_ex).unwrap()));
-        let second_case = SudoMsg {
-            data: vec![GenericVE {
-                vote: cosmwasm_std::Binary(serialize(&vote_ex).unwrap()),
-                ve_power: 1000,
-            }],
-        };
+    fn save_delegated(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        delegated: Vec<Coin>,
+    ) -> StdResult<()> {
+        if delegated.is_empty() {
+            DELEGATED.remove(storage, operator.clone());
+            Ok(())
+        } else {
+            DELEGATED.save(storage, operator.clone(), &delegated)
+        }
+    }
 
-        assert!(Ok(Response::new()).eq(&sudo(deps.as_mut(), mock_env(), second_case)));
+    fn save_undelegated(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        undelegated: Vec<Coin>,
+    ) -> StdResult<()> {
+        if undelegated.is_empty() {
+            UNDELEGATED.remove(storage, operator.clone());
+            Ok(())
+        } else {
+            UNDELEGATED.save(storage, operator.clone(), &undelegated)
+        }
+    }
 
-        println!(
-            "{:?}",
-            query(
-                deps.as_ref(),
-                mock_env(),
-                QueryMsg::LookupHash {
-                    chain_id: "foo".to_string(),
-                    hash: Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg").unwrap()
-                }
-            )
-            .unwrap()
-        );
+    /// realize_slashes takes the slashes of `operator` since the delegation and
+    /// undelegations of `delegator` were last updated out of them. Both have to be
+    /// realized before either of them changes.
+    fn realize_slashes(
+        storage: &mut dyn Storage,
+        config: &Config,
+        operator: &Addr,
+        delegator: &Addr,
+    ) -> ContractResult<()> {
+        let (slash_factor, recorded) = slash_factors(storage, operator, delegator)?;
+        if slash_factor == recorded {
+            return Ok(());
+        }
+        // both are loaded before saving either records the current slash factor
+        let amount = delegation(storage, operator, delegator)?;
+        let queue = undelegations(storage, operator, delegator)?;
+        if let Some(amount) = amount {
+            save_delegation(storage, config, operator, delegator, amount)?;
+        }
+        if let Some(queue) = queue {
+            save_undelegations(storage, operator, delegator, queue)?;
+        }
+        Ok(())
+    }
+
+    /// save_delegation stores the delegation of `delegator` to `operator`, removing it
+    /// once empty, keeps the operators of the delegator in sync and updates the shares
+    /// the delegation earns rewards with.
+    fn save_delegation(
+        storage: &mut dyn Storage,
+        config: &Config,
+        operator: &Addr,
+        delegator: &Addr,
+        amount: Vec<Coin>,
+    ) -> ContractResult<()> {
+        let key = (operator.clone(), delegator.clone());
+        if amount.is_empty() {
+            DELEGATIONS.remove(storage, key);
+        } else {
+            DELEGATIONS.save(storage, key, &amount)?;
+        }
+        sync_delegator_operator(storage, operator, delegator)?;
+        rewards::update_shares(storage, config, delegator, operator, &amount)
+    }
+
+    /// save_undelegations stores the undelegations of `delegator` from `operator`,
+    /// removing them once empty, and keeps the operators of the delegator in sync.
+    fn save_undelegations(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        delegator: &Addr,
+        queue: Vec<Unbonding>,
+    ) -> StdResult<()> {
+        let key = (operator.clone(), delegator.clone());
+        if queue.is_empty() {
+            UNDELEGATIONS.remove(storage, key);
+        } else {
+            UNDELEGATIONS.save(storage, key, &queue)?;
+        }
+        sync_delegator_operator(storage, operator, delegator)
+    }
+
+    /// sync_delegator_operator indexes the operator for the delegator while it holds a
+    /// delegation or undelegations with it, and records the slash factor they are
+    /// stored at.
+    fn sync_delegator_operator(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        delegator: &Addr,
+    ) -> StdResult<()> {
+        let key = (operator.clone(), delegator.clone());
+        let index_key = (delegator.clone(), operator.clone());
+        if DELEGATIONS.has(storage, key.clone()) || UNDELEGATIONS.has(storage, key.clone()) {
+            let slash_factor = SLASH_FACTORS
+                .may_load(storage, operator.clone())?
+                .unwrap_or_default();
+            DELEGATION_SLASH_FACTORS.save(storage, key, &slash_factor)?;
+            DELEGATOR_OPERATORS.save(storage, index_key, &Empty {})
+        } else {
+            DELEGATION_SLASH_FACTORS.remove(storage, key);
+            DELEGATOR_OPERATORS.remove(storage, index_key);
+            Ok(())
+        }
+    }
+
+    /// fund_rewards adds the sent funds to the reward pool.
+    pub fn fund_rewards(deps: DepsMut, info: MessageInfo) -> ContractResult<Response> {
+        let config = CONFIG.load(deps.storage)?;
+        let denom = config
+            .rewards
+            .map(|rewards| rewards.denom)
+            .ok_or(ContractError::RewardsDisabled {})?;
+        let mut funded = Uint128::zero();
+        for coin in info.funds {
+            if coin.denom != denom {
+                return Err(ContractError::InvalidRewardFunds { denom });
+            }
+            funded = funded.checked_add(coin.amount)?;
+        }
+        let pool = REWARD_POOL.may_load(deps.storage)?.unwrap_or_default();
+        REWARD_POOL.save(deps.storage, &pool.checked_add(funded)?)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "fund_rewards")
+            .add_attribute("amount", Coin::new(funded.u128(), denom).to_string()))
+    }
+
+    /// claim_rewards pays out the rewards the sender earned with every operator.
+    pub fn claim_rewards(deps: DepsMut, info: MessageInfo) -> ContractResult<Response> {
+        let config = CONFIG.load(deps.storage)?;
+        let denom = config
+            .rewards
+            .map(|rewards| rewards.denom)
+            .ok_or(ContractError::RewardsDisabled {})?;
+        let claimed = rewards::claim(deps.storage, &info.sender)?;
+        if claimed.is_zero() {
+            return Err(ContractError::NoRewards {});
+        }
+
+        Ok(Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin::new(claimed.u128(), denom)],
+            })
+            .add_attribute("action", "claim_rewards")
+            .add_attribute("amount", claimed))
+    }
+
+    /// reweight recomputes the reward shares of up to `limit` stakes after the denom
+    /// weights changed. Anyone can call it until every share is up to date.
+    pub fn reweight(deps: DepsMut, limit: Option<u32>) -> ContractResult<Response> {
+        if !REWEIGHT.exists(deps.storage) {
+            return Err(ContractError::NothingToReweight {});
+        }
+        let config = CONFIG.load(deps.storage)?;
+        let limit = limit
+            .unwrap_or(DEFAULT_REWEIGHT_LIMIT)
+            .min(MAX_REWEIGHT_LIMIT) as usize;
+        let done = rewards::reweight(deps.storage, &config, limit)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "reweight")
+            .add_attribute("done", done.to_string()))
+    }
+
+    /// set_commission sets the commission rate of the sender, which has to be a
+    /// registered operator.
+    pub fn set_commission(
+        deps: DepsMut,
+        info: MessageInfo,
+        rate: Decimal,
+    ) -> ContractResult<Response> {
+        if rate > Decimal::one() {
+            return Err(ContractError::InvalidCommission {});
+        }
+        let mut operator = OPERATORS
+            .may_load(deps.storage, info.sender.clone())?
+            .ok_or_else(|| ContractError::OperatorKeyNotRegistered {
+                operator: info.sender.to_string(),
+            })?;
+        operator.commission = rate;
+        OPERATORS.save(deps.storage, info.sender.clone(), &operator)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_commission")
+            .add_attribute("operator", info.sender)
+            .add_attribute("rate", rate.to_string()))
+    }
+
+    /// register_operator sets the key the sender signs its votes with. A new operator
+    /// uses its key right away. A registered operator rotates to the key once the key
+    /// rotation delay has passed, replacing any rotation still in progress.
+    pub fn register_operator(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        pubkey: Binary,
+        key_type: KeyType,
+    ) -> ContractResult<Response> {
+        let key = OperatorKey { key_type, pubkey };
+        validate_operator_key(&key)?;
+
+        let registered = OPERATORS.has(deps.storage, info.sender.clone());
+        let operator = match OPERATORS.may_load(deps.storage, info.sender.clone())? {
+            None => OperatorInfo {
+                key,
+                pending_key: None,
+                commission: Decimal::zero(),
+            },
+            Some(operator) => {
+                let config = CONFIG.load(deps.storage)?;
+                OperatorInfo {
+                    key: operator.keys(env.block.time)[0].clone(),
+                    pending_key: Some(PendingKey {
+                        key,
+                        active_at: env.block.time.plus_seconds(config.key_rotation_delay),
+                    }),
+                    commission: operator.commission,
+                }
+            }
+        };
+        let active_at = operator
+            .pending_key
+            .as_ref()
+            .map_or(env.block.time, |pending| pending.active_at);
+        OPERATORS.save(deps.storage, info.sender.clone(), &operator)?;
+        // stake held before registering starts counting towards the bonded power
+        if !registered {
+            let stake = operator_stake(deps.storage, &info.sender)?;
+            add_bonded(deps.storage, &info.sender, &stake)?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "register_operator")
+            .add_attribute("operator", info.sender)
+            .add_attribute("active_at", active_at.to_string()))
+    }
+
+    /// submit_evidence slashes and jails `operator` if `vote_a` and `vote_b` are two
+    /// of its signed votes for different roots of the same chain and height. The
+    /// sender is paid the reporter bounty out of the slashed stake, the rest is burned.
+    pub fn submit_evidence(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        operator: String,
+        vote_a: SignedVote,
+        vote_b: SignedVote,
+    ) -> ContractResult<Response> {
+        let operator = deps.api.addr_validate(&operator)?;
+        let invalid = |reason: &str| ContractError::InvalidEvidence {
+            reason: reason.to_string(),
+        };
+        if vote_a.chain_id != vote_b.chain_id || vote_a.root.height != vote_b.root.height {
+            return Err(invalid("votes are for different chains or heights"));
+        }
+        if vote_a.root.root == vote_b.root.root {
+            return Err(invalid("votes are for the same root"));
+        }
+        let key = (
+            operator.clone(),
+            vote_a.chain_id.clone(),
+            vote_a.root.height,
+        );
+        if EVIDENCE.has(deps.storage, key.clone()) {
+            return Err(ContractError::EvidenceAlreadySubmitted {
+                operator: operator.to_string(),
+                chain_id: vote_a.chain_id,
+                height: vote_a.root.height,
+            });
+        }
+        let operator_info = OPERATORS
+            .may_load(deps.storage, operator.clone())?
+            .ok_or_else(|| ContractError::OperatorKeyNotRegistered {
+                operator: operator.to_string(),
+            })?;
+        // the votes may be signed with the current key or the key being rotated to
+        let keys = operator_info.keys(env.block.time);
+        for vote in [&vote_a, &vote_b] {
+            let sign_bytes = vote.sign_bytes()?;
+            if !keys
+                .iter()
+                .any(|key| verify_signature(deps.api, key, &sign_bytes, &vote.signature))
+            {
+                return Err(invalid("signature does not match the operator key"));
+            }
+        }
+        EVIDENCE.save(deps.storage, key, &env.block.time)?;
+
+        let config = CONFIG.load(deps.storage)?;
+        let slashed = slash(
+            deps.storage,
+            &config,
+            &operator,
+            config.slashing.slash_fraction,
+        )?;
+        let (burned, bounty) = split_coins(slashed, config.slashing.reporter_bounty);
+        let jailed_until = env.block.time.plus_seconds(config.slashing.jail_duration);
+        jail(deps.storage, &operator, jailed_until)?;
+
+        let mut res = Response::new()
+            .add_attribute("action", "submit_evidence")
+            .add_attribute("operator", operator)
+            .add_attribute("chain_id", vote_a.chain_id)
+            .add_attribute("height", vote_a.root.height.to_string())
+            .add_attribute("jailed_until", jailed_until.to_string());
+        if !bounty.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: bounty,
+            });
+        }
+        if !burned.is_empty() {
+            res = res.add_message(BankMsg::Burn { amount: burned });
+        }
+        Ok(res)
+    }
+
+    /// jail keeps `operator` from voting with any power until `jailed_until`, unless
+    /// it is already jailed for longer. Its stake stops counting towards the bonded
+    /// power until the jail term is released.
+    pub(crate) fn jail(
+        storage: &mut dyn Storage,
+        operator: &Addr,
+        jailed_until: Timestamp,
+    ) -> ContractResult<()> {
+        let stake = operator_stake(storage, operator)?;
+        sub_bonded(storage, operator, &stake)?;
+        let current = JAILED.may_load(storage, operator.clone())?;
+        if let Some(current) = current {
+            JAIL_RELEASES.remove(storage, (current.nanos(), operator.clone()));
+        }
+        let jailed_until = current.map_or(jailed_until, |current| current.max(jailed_until));
+        JAILED.save(storage, operator.clone(), &jailed_until)?;
+        JAIL_RELEASES.save(storage, (jailed_until.nanos(), operator.clone()), &Empty {})?;
+        // votes carried over from before the operator was jailed must not count either
+        let chain_ids: Vec<String> = OPERATOR_PENDING_VOTES
+            .prefix(operator.to_string())
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        for chain_id in chain_ids {
+            remove_pending_vote(storage, chain_id, operator.to_string());
+        }
+        Ok(())
+    }
+
+    /// slash takes `fraction` of the bonded and unbonding stake of `operator` and
+    /// returns the slashed coins. The delegations and undelegations to the operator are
+    /// only slashed in total here, each of them is scaled down by the slash factor of
+    /// the operator once it is next updated, so slashing does not depend on the number
+    /// of delegators.
+    pub(crate) fn slash(
+        storage: &mut dyn Storage,
+        config: &Config,
+        operator: &Addr,
+        fraction: Decimal,
+    ) -> ContractResult<Vec<Coin>> {
+        let slash_factor = SLASH_FACTORS
+            .may_load(storage, operator.clone())?
+            .unwrap_or_default();
+        SLASH_FACTORS.save(storage, operator.clone(), &slash_factor.slash(fraction))?;
+
+        // bonded coins taken, which no longer count towards the total stake
+        let mut bonded: Vec<Coin> = vec![];
+        if let Some(staked_coins) = STAKE_MAP.may_load(storage, operator.clone())? {
+            let (staked_coins, taken) = split_coins(staked_coins, fraction);
+            save_stake(storage, config, operator, staked_coins)?;
+            bonded = add_coins(bonded, &taken)?;
+        }
+        // rounding down what is taken keeps the totals covering every delegation
+        if let Some(delegated) = DELEGATED.may_load(storage, operator.clone())? {
+            let (delegated, taken) = split_coins(delegated, fraction);
+            save_delegated(storage, operator, delegated)?;
+            bonded = add_coins(bonded, &taken)?;
+        }
+        let total_stake = TOTAL_STAKE.may_load(storage)?.unwrap_or_default();
+        TOTAL_STAKE.save(storage, &sub_coins(total_stake, &bonded)?)?;
+        sub_bonded(storage, operator, &bonded)?;
+
+        let mut slashed = bonded;
+        if let Some(queue) = UNBONDING_QUEUE.may_load(storage, operator.clone())? {
+            let queue = slash_unbondings(queue, fraction, &mut slashed)?;
+            if queue.is_empty() {
+                UNBONDING_QUEUE.remove(storage, operator.clone());
+            } else {
+                UNBONDING_QUEUE.save(storage, operator.clone(), &queue)?;
+            }
+        }
+        if let Some(undelegated) = UNDELEGATED.may_load(storage, operator.clone())? {
+            let (undelegated, taken) = split_coins(undelegated, fraction);
+            save_undelegated(storage, operator, undelegated)?;
+            slashed = add_coins(slashed, &taken)?;
+        }
+        Ok(slashed)
+    }
+
+    /// slash_unbondings takes `fraction` of every entry of `queue` into `slashed` and
+    /// returns the entries that are left.
+    fn slash_unbondings(
+        queue: Vec<Unbonding>,
+        fraction: Decimal,
+        slashed: &mut Vec<Coin>,
+    ) -> ContractResult<Vec<Unbonding>> {
+        let mut remaining: Vec<Unbonding> = vec![];
+        for unbonding in queue {
+            let (amount, taken) = split_coins(unbonding.amount, fraction);
+            *slashed = add_coins(std::mem::take(slashed), &taken)?;
+            if !amount.is_empty() {
+                remaining.push(Unbonding {
+                    amount,
+                    release_at: unbonding.release_at,
+                });
+            }
+        }
+        Ok(remaining)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_chain(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        family: ChainFamily,
+        caip2: String,
+        hash_function: HashFunction,
+        root_kind: RootKind,
+        retention: Option<Retention>,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        if CHAINS.has(deps.storage, chain_id.clone()) {
+            return Err(ContractError::ChainAlreadyRegistered { chain_id });
+        }
+        validate_caip2(&caip2)?;
+        if let Some(retention) = &retention {
+            validate_retention(retention)?;
+        }
+
+        CHAINS.save(
+            deps.storage,
+            chain_id.clone(),
+            &ChainInfo {
+                family,
+                caip2,
+                hash_function,
+                root_kind,
+                paused: false,
+            },
+        )?;
+        save_chain_config(
+            deps.storage,
+            &chain_id,
+            &ChainConfig {
+                retention,
+                ..Default::default()
+            },
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("action", "register_chain")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    pub fn set_chain_paused(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        paused: bool,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        let mut chain = assert_registered(deps.as_ref(), &chain_id)?;
+        chain.paused = paused;
+        CHAINS.save(deps.storage, chain_id.clone(), &chain)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_paused")
+            .add_attribute("chain_id", chain_id)
+            .add_attribute("paused", paused.to_string()))
+    }
+
+    /// deregister_chain removes every trace of a chain, so registering it again starts
+    /// from an empty history. The number of roots removed is bounded by the retention
+    /// of the chain.
+    pub fn deregister_chain(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+
+        CHAINS.remove(deps.storage, chain_id.clone());
+        CHAIN_CONFIGS.remove(deps.storage, chain_id.clone());
+        SUBMITTERS.remove(deps.storage, chain_id.clone());
+        clear_pending_votes(deps.storage, &chain_id)?;
+        let submissions: Vec<(Vec<u8>, u64)> = ROOT_SUBMISSIONS
+            .sub_prefix(chain_id.clone())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        for (root, height) in submissions {
+            ROOT_SUBMISSIONS.remove(deps.storage, (chain_id.clone(), root, height));
+        }
+        let roots: Vec<(u64, FinalizedRoot)> = ROOTS
+            .prefix(chain_id.clone())
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+        for (seq, root) in roots {
+            ROOTS.remove(deps.storage, (chain_id.clone(), seq));
+            ROOT_INDEX.remove(deps.storage, (chain_id.clone(), root.root.to_vec()));
+            ATTESTATIONS.remove(deps.storage, (chain_id.clone(), root.root.to_vec()));
+        }
+        ROOT_CURSORS.remove(deps.storage, chain_id.clone());
+
+        Ok(Response::new()
+            .add_attribute("action", "deregister_chain")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    pub fn update_config(
+        deps: DepsMut,
+        info: MessageInfo,
+        update: ConfigUpdate,
+    ) -> ContractResult<Response> {
+        let ConfigUpdate {
+            unbonding_period,
+            power_source,
+            denom_weights,
+            default_threshold,
+            default_strategy,
+            default_vote_window,
+            default_retention,
+            slashing,
+            key_rotation_delay,
+            rewards,
+            liveness,
+            record_attestations,
+        } = update;
+        assert_admin(deps.as_ref(), &info)?;
+
+        let mut config = CONFIG.load(deps.storage)?;
+        if let Some(unbonding_period) = unbonding_period {
+            config.unbonding_period = unbonding_period;
+        }
+        if let Some(power_source) = power_source {
+            config.power_source = power_source;
+        }
+        let reweight = denom_weights
+            .as_ref()
+            .is_some_and(|weights| *weights != config.denom_weights);
+        if let Some(denom_weights) = denom_weights {
+            config.denom_weights = denom_weights;
+        }
+        if let Some(default_threshold) = default_threshold {
+            validate_threshold(default_threshold)?;
+            config.default_threshold = default_threshold;
+        }
+        if let Some(default_strategy) = default_strategy {
+            validate_strategy(&default_strategy)?;
+            config.default_strategy = default_strategy;
+        }
+        if let Some(default_vote_window) = default_vote_window {
+            validate_vote_window(default_vote_window)?;
+            config.default_vote_window = default_vote_window;
+        }
+        if let Some(default_retention) = default_retention {
+            validate_retention(&default_retention)?;
+            config.default_retention = default_retention;
+        }
+        if let Some(slashing) = slashing {
+            validate_slashing(&slashing)?;
+            config.slashing = slashing;
+        }
+        if let Some(key_rotation_delay) = key_rotation_delay {
+            config.key_rotation_delay = key_rotation_delay;
+        }
+        if let Some(rewards) = rewards {
+            if config
+                .rewards
+                .as_ref()
+                .is_some_and(|current| current.denom != rewards.denom)
+            {
+                return Err(ContractError::RewardDenomChanged {});
+            }
+            config.rewards = Some(rewards);
+        }
+        if let Some(liveness) = liveness {
+            validate_liveness(&liveness)?;
+            config.liveness = liveness;
+        }
+        if let Some(record_attestations) = record_attestations {
+            config.record_attestations = record_attestations;
+        }
+        // shares are recomputed in batches through `Reweight`, starting over if a
+        // previous change of the weights is still pending
+        if reweight {
+            REWEIGHT.save(deps.storage, &Reweight::Stakes { start_after: None })?;
+        }
+        CONFIG.save(deps.storage, &config)?;
+
+        Ok(Response::new().add_attribute("action", "update_config"))
+    }
+
+    pub fn set_chain_threshold(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        threshold: Option<Decimal>,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+        if let Some(threshold) = threshold {
+            validate_threshold(threshold)?;
+        }
+
+        let mut chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        chain_config.threshold = threshold;
+        save_chain_config(deps.storage, &chain_id, &chain_config)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_threshold")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    pub fn set_chain_strategy(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        strategy: Option<Strategy>,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+        if let Some(strategy) = &strategy {
+            validate_strategy(strategy)?;
+        }
+
+        let mut chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        chain_config.strategy = strategy;
+        save_chain_config(deps.storage, &chain_id, &chain_config)?;
+        // votes carried over under the previous strategy must not leak into the new one
+        clear_pending_votes(deps.storage, &chain_id)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_strategy")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    pub fn set_chain_vote_window(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        vote_window: Option<u64>,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+        if let Some(vote_window) = vote_window {
+            validate_vote_window(vote_window)?;
+        }
+
+        let mut chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        chain_config.vote_window = vote_window;
+        save_chain_config(deps.storage, &chain_id, &chain_config)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_vote_window")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    /// submit_root records the confirmation of a root by one of the chain's submitters
+    /// and writes the root once enough submitters agree on it. Confirmations of
+    /// addresses that have since been removed from the chain's submitters are ignored.
+    pub fn submit_root(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        chain_id: String,
+        root: Binary,
+        height: u64,
+    ) -> ContractResult<Response> {
+        if is_paused(deps.storage)? {
+            return Err(ContractError::ContractPaused {});
+        }
+        if assert_registered(deps.as_ref(), &chain_id)?.paused {
+            return Err(ContractError::ChainPaused { chain_id });
+        }
+        let submitter_set = SUBMITTERS
+            .may_load(deps.storage, chain_id.clone())?
+            .ok_or(ContractError::Unauthorized {})?;
+        if !submitter_set.submitters.contains(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        if let Some(oldest) = oldest_root_height(deps.storage, &chain_id)? {
+            // such a root would be evicted as soon as it is finalized
+            if height < oldest {
+                return Err(ContractError::StaleRoot { chain_id, height });
+            }
+            prune_submissions(deps.storage, &chain_id, oldest)?;
+        }
+
+        let key = (chain_id.clone(), root.to_vec(), height);
+        let mut confirmations = ROOT_SUBMISSIONS
+            .may_load(deps.storage, key.clone())?
+            .unwrap_or_default();
+        confirmations.retain(|submitter| submitter_set.submitters.contains(submitter));
+        if confirmations.contains(&info.sender) {
+            return Err(ContractError::DuplicateSubmission {
+                submitter: info.sender.to_string(),
+            });
+        }
+        confirmations.push(info.sender);
+
+        let res = Response::new()
+            .add_attribute("action", "submit_root")
+            .add_attribute("chain_id", chain_id.clone())
+            .add_attribute("confirmations", confirmations.len().to_string());
+        if (confirmations.len() as u64) < submitter_set.threshold {
+            ROOT_SUBMISSIONS.save(deps.storage, key, &confirmations)?;
+            return Ok(res);
+        }
+
+        ROOT_SUBMISSIONS.remove(deps.storage, key);
+        let written = write_merkle_roots(
+            deps,
+            &env.block,
+            vec![(
+                chain_id,
+                FinalizedRoot {
+                    root,
+                    source_height: height,
+                    finalized_height: env.block.height,
+                    finalized_at: env.block.time,
+                    power: Uint128::zero(),
+                },
+                None,
+            )],
+        )?;
+        Ok(res
+            .add_attribute("finalized", "true")
+            .add_events(written.events))
+    }
+
+    /// oldest_root_height returns the source height of the oldest root kept for the
+    /// chain, if any.
+    fn oldest_root_height(storage: &dyn Storage, chain_id: &str) -> StdResult<Option<u64>> {
+        let Some(cursor) = ROOT_CURSORS.may_load(storage, chain_id.to_string())? else {
+            return Ok(None);
+        };
+        Ok(ROOTS
+            .may_load(storage, (chain_id.to_string(), cursor.first_seq))?
+            .map(|root| root.source_height))
+    }
+
+    /// prune_submissions removes the submissions for roots below `height` that never
+    /// reached the threshold of the chain.
+    fn prune_submissions(storage: &mut dyn Storage, chain_id: &str, height: u64) -> StdResult<()> {
+        let stale: Vec<(Vec<u8>, u64)> = ROOT_SUBMISSIONS
+            .sub_prefix(chain_id.to_string())
+            .keys(storage, None, None, Order::Ascending)
+            .filter(|key| {
+                key.as_ref()
+                    .map_or(true, |(_, submitted)| *submitted < height)
+            })
+            .collect::<StdResult<_>>()?;
+        for (root, submitted) in stale {
+            ROOT_SUBMISSIONS.remove(storage, (chain_id.to_string(), root, submitted));
+        }
+        Ok(())
+    }
+
+    pub fn set_chain_submitters(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        submitters: Vec<String>,
+        threshold: u64,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+
+        let mut addresses: Vec<Addr> = Vec::with_capacity(submitters.len());
+        for submitter in submitters {
+            let address = deps.api.addr_validate(&submitter)?;
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        if addresses.is_empty() {
+            SUBMITTERS.remove(deps.storage, chain_id.clone());
+        } else {
+            if threshold == 0 || threshold > addresses.len() as u64 {
+                return Err(ContractError::InvalidSubmitterThreshold {});
+            }
+            SUBMITTERS.save(
+                deps.storage,
+                chain_id.clone(),
+                &SubmitterSet {
+                    submitters: addresses,
+                    threshold,
+                },
+            )?;
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_submitters")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    pub fn set_chain_retention(
+        deps: DepsMut,
+        info: MessageInfo,
+        chain_id: String,
+        retention: Option<Retention>,
+    ) -> ContractResult<Response> {
+        assert_admin(deps.as_ref(), &info)?;
+        assert_registered(deps.as_ref(), &chain_id)?;
+        if let Some(retention) = &retention {
+            validate_retention(retention)?;
+        }
+
+        let mut chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        chain_config.retention = retention;
+        save_chain_config(deps.storage, &chain_id, &chain_config)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_chain_retention")
+            .add_attribute("chain_id", chain_id))
+    }
+
+    /// save_chain_config stores the overrides of a chain, dropping the entry entirely
+    /// once nothing is overridden anymore.
+    fn save_chain_config(
+        storage: &mut dyn Storage,
+        chain_id: &str,
+        chain_config: &ChainConfig,
+    ) -> StdResult<()> {
+        if *chain_config == ChainConfig::default() {
+            CHAIN_CONFIGS.remove(storage, chain_id.to_string());
+            return Ok(());
+        }
+        CHAIN_CONFIGS.save(storage, chain_id.to_string(), chain_config)
+    }
+
+    /// write_merkle_roots implements the state update method of the contract.
+    /// Merkle roots are input using a map of chain_id to finalized root, along with the
+    /// attestation of the votes that finalized it, if any.
+    /// Every chain keeps its roots in a ring buffer of sequence numbers, so a root
+    /// that is already known is found through the index and, once a chain holds more
+    /// than `max_roots` of its retention, only its oldest entries are evicted. Each write
+    /// touches a bounded number of storage entries regardless of the cache size.
+    pub fn write_merkle_roots(
+        deps: DepsMut,
+        block: &BlockInfo,
+        merkle_roots: Vec<(String, FinalizedRoot, Option<Attestation>)>,
+    ) -> Result<Response, ContractError> {
+        let config = CONFIG.load(deps.storage)?;
+        let mut events = vec![];
+        for (chain_id, merkle_root, attestation) in merkle_roots {
+            let index_key = (chain_id.clone(), merkle_root.root.to_vec());
+            let mut cursor = ROOT_CURSORS
+                .may_load(deps.storage, chain_id.clone())?
+                .unwrap_or(RootCursor {
+                    first_seq: 0,
+                    next_seq: 0,
+                });
+            let retention = chain_retention(deps.storage, &config, &chain_id)?;
+            if let Some(seq) = ROOT_INDEX.may_load(deps.storage, index_key.clone())? {
+                let stored = ROOTS.load(deps.storage, (chain_id.clone(), seq))?;
+                if !root_expired(&retention, &stored, cursor.next_seq - seq, block) {
+                    events.push(
+                        Event::new("duplicate_root")
+                            .add_attribute("chain_id", &chain_id)
+                            .add_attribute("root", hex::encode(&merkle_root.root))
+                            .add_attribute("seq", seq.to_string()),
+                    );
+                    continue;
+                }
+                // an expired root is evicted and finalized again as the latest root
+                ROOTS.remove(deps.storage, (chain_id.clone(), seq));
+                ROOT_INDEX.remove(deps.storage, index_key.clone());
+                ATTESTATIONS.remove(deps.storage, index_key.clone());
+                events.push(
+                    Event::new("root_evicted")
+                        .add_attribute("chain_id", &chain_id)
+                        .add_attribute("root", hex::encode(&merkle_root.root))
+                        .add_attribute("seq", seq.to_string()),
+                );
+            }
+            ROOTS.save(
+                deps.storage,
+                (chain_id.clone(), cursor.next_seq),
+                &merkle_root,
+            )?;
+            ROOT_INDEX.save(deps.storage, index_key.clone(), &cursor.next_seq)?;
+            let mut event = Event::new("root_finalized")
+                .add_attribute("chain_id", &chain_id)
+                .add_attribute("root", hex::encode(&merkle_root.root))
+                .add_attribute("source_height", merkle_root.source_height.to_string())
+                .add_attribute("seq", cursor.next_seq.to_string())
+                .add_attribute("power", merkle_root.power);
+            if let Some(attestation) = attestation {
+                event = event.add_attributes(participation_attributes(
+                    attestation.power_for + attestation.power_against,
+                    attestation.total_power,
+                ));
+                if config.record_attestations {
+                    ATTESTATIONS.save(deps.storage, index_key, &attestation)?;
+                }
+            }
+            events.push(event);
+            cursor.next_seq += 1;
+
+            for _ in 0..MAX_EVICTIONS_PER_WRITE {
+                if cursor.next_seq - cursor.first_seq <= retention.max_roots {
+                    break;
+                }
+                let evicted_seq = cursor.first_seq;
+                if let Some(evicted) =
+                    ROOTS.may_load(deps.storage, (chain_id.clone(), evicted_seq))?
+                {
+                    ROOTS.remove(deps.storage, (chain_id.clone(), evicted_seq));
+                    ROOT_INDEX.remove(deps.storage, (chain_id.clone(), evicted.root.to_vec()));
+                    ATTESTATIONS.remove(deps.storage, (chain_id.clone(), evicted.root.to_vec()));
+                    events.push(
+                        Event::new("root_evicted")
+                            .add_attribute("chain_id", &chain_id)
+                            .add_attribute("root", hex::encode(&evicted.root))
+                            .add_attribute("seq", evicted_seq.to_string()),
+                    );
+                }
+                cursor.first_seq += 1;
+            }
+            ROOT_CURSORS.save(deps.storage, chain_id, &cursor)?;
+        }
+        Ok(Response::new().add_events(events))
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::LookupHash { chain_id, hash } => {
+            to_json_binary(&query::lookup_hash(deps, env, chain_id, hash)?)
+        }
+        QueryMsg::LookupRoot { chain_id, hash } => {
+            to_json_binary(&query::lookup_root(deps, env, chain_id, hash)?)
+        }
+        QueryMsg::PendingUnbondings { address } => {
+            to_json_binary(&query::pending_unbondings(deps, address)?)
+        }
+        QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+        QueryMsg::Ownership {} => to_json_binary(&OWNERSHIP.load(deps.storage)?),
+        QueryMsg::PendingVotes { chain_id } => {
+            to_json_binary(&query::pending_votes(deps, env, chain_id)?)
+        }
+        QueryMsg::Submitters { chain_id } => to_json_binary(&query::submitters(deps, chain_id)?),
+        QueryMsg::ChainInfo { chain_id } => to_json_binary(&query::chain_info(deps, chain_id)?),
+        QueryMsg::ListChains { start_after, limit } => {
+            to_json_binary(&query::list_chains(deps, start_after, limit)?)
+        }
+        QueryMsg::LatestRoot { chain_id } => {
+            to_json_binary(&query::latest_root(deps, env, chain_id)?)
+        }
+        QueryMsg::RootAttestation { chain_id, root } => {
+            to_json_binary(&query::root_attestation(deps, chain_id, root)?)
+        }
+        QueryMsg::ListRoots {
+            chain_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query::list_roots(deps, env, chain_id, start_after, limit)?),
+        QueryMsg::StakeOf { address } => to_json_binary(&query::stake_of(deps, address)?),
+        QueryMsg::ListStakers { start_after, limit } => {
+            to_json_binary(&query::list_stakers(deps, start_after, limit)?)
+        }
+        QueryMsg::TotalStake {} => to_json_binary(&query::total_stake(deps, env)?),
+        QueryMsg::SimulateAggregation { data, total_power } => {
+            to_json_binary(&query::simulate_aggregation(deps, env, data, total_power)?)
+        }
+        QueryMsg::Operator { address } => to_json_binary(&query::operator(deps, env, address)?),
+        QueryMsg::PendingRewards { address } => {
+            to_json_binary(&query::pending_rewards(deps, address)?)
+        }
+        QueryMsg::SigningInfo { operator } => to_json_binary(&query::signing_info(deps, operator)?),
+        QueryMsg::GetPrice { pair } => to_json_binary(&query::price(deps, pair)?),
+        QueryMsg::GetPrices { pairs } => to_json_binary(&query::prices(deps, pairs)?),
+        QueryMsg::OperatorDelegations {
+            operator,
+            start_after,
+            limit,
+        } => to_json_binary(&query::operator_delegations(
+            deps,
+            operator,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::DelegatorDelegations {
+            delegator,
+            start_after,
+            limit,
+        } => to_json_binary(&query::delegator_delegations(
+            deps,
+            delegator,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+pub mod query {
+    use super::*;
+    use crate::msg::{
+        ChainAggregationResponse, ChainInfoResponse, ConfigResponse, DelegationsResponse,
+        ListChainsResponse, ListRootsResponse, ListStakersResponse, OperatorResponse,
+        PendingRewardsResponse, PendingRoot, PendingUnbondingsResponse, PendingUndelegation,
+        PendingVotesResponse, PriceResponse, PricesResponse, RootResponse,
+        SimulateAggregationResponse, StakeResponse, SubmittersResponse, TotalStakeResponse,
+    };
+    use crate::state::{
+        Delegation, SigningInfo, DELEGATIONS, DELEGATOR_OPERATORS, OPERATORS, PRICES, REWARD_POOL,
+        SIGNING_INFOS, SUBMITTERS, UNBONDING_QUEUE,
+    };
+    use cw_storage_plus::Bound;
+
+    pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
+        Ok(ConfigResponse {
+            config: CONFIG.load(deps.storage)?,
+            paused: is_paused(deps.storage)?,
+        })
+    }
+
+    /// pending_votes sums the carried over votes of a chain per root.
+    pub fn pending_votes(
+        deps: Deps,
+        env: Env,
+        chain_id: String,
+    ) -> StdResult<PendingVotesResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        let strategy = chain_config
+            .strategy
+            .clone()
+            .unwrap_or_else(|| config.default_strategy.clone());
+        let window = vote_window(&config, &chain_config, &strategy);
+
+        let mut roots: BTreeMap<SourceRoot, PendingRoot> = BTreeMap::new();
+        for item in PENDING_VOTES
+            .prefix(chain_id)
+            .range(deps.storage, None, None, Order::Ascending)
+        {
+            let (operator, pending_vote) = item?;
+            // expired votes are only pruned when the chain is aggregated again
+            if pending_vote.height.saturating_add(window) <= env.block.height {
+                continue;
+            }
+            let pending_root =
+                roots
+                    .entry(pending_vote.root.clone())
+                    .or_insert_with(|| PendingRoot {
+                        root: pending_vote.root,
+                        power: Uint128::zero(),
+                        operators: vec![],
+                    });
+            pending_root.power = pending_root.power.checked_add(pending_vote.power)?;
+            pending_root.operators.push(operator);
+        }
+        Ok(PendingVotesResponse {
+            roots: roots.into_values().collect(),
+        })
+    }
+
+    pub fn chain_info(deps: Deps, chain_id: String) -> StdResult<ChainInfoResponse> {
+        let info = CHAINS.load(deps.storage, chain_id.clone())?;
+        chain_info_response(deps, &CONFIG.load(deps.storage)?, chain_id, info)
+    }
+
+    pub fn list_chains(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListChainsResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+        let chains = CHAINS
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (chain_id, info) = item?;
+                chain_info_response(deps, &config, chain_id, info)
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(ListChainsResponse { chains })
+    }
+
+    /// latest_root returns the last root finalized for a chain, whether or not it has
+    /// expired since.
+    pub fn latest_root(deps: Deps, env: Env, chain_id: String) -> StdResult<RootResponse> {
+        let (seq, root) = ROOTS
+            .prefix(chain_id.clone())
+            .range(deps.storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .ok_or_else(|| StdError::not_found(format!("root of {chain_id}")))?;
+        let retention = chain_retention(deps.storage, &CONFIG.load(deps.storage)?, &chain_id)?;
+        root_response(deps, &env, &retention, chain_id, seq, root)
+    }
+
+    pub fn root_attestation(deps: Deps, chain_id: String, root: Binary) -> StdResult<Attestation> {
+        ATTESTATIONS
+            .may_load(deps.storage, (chain_id.clone(), root.to_vec()))?
+            .ok_or_else(|| StdError::not_found(format!("attestation of {root} on {chain_id}")))
+    }
+
+    /// list_roots lists the roots stored for a chain, oldest first.
+    pub fn list_roots(
+        deps: Deps,
+        env: Env,
+        chain_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ListRootsResponse> {
+        let retention = chain_retention(deps.storage, &CONFIG.load(deps.storage)?, &chain_id)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+        let roots = ROOTS
+            .prefix(chain_id.clone())
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (seq, root) = item?;
+                root_response(deps, &env, &retention, chain_id.clone(), seq, root)
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(ListRootsResponse { roots })
+    }
+
+    fn root_response(
+        deps: Deps,
+        env: &Env,
+        retention: &Retention,
+        chain_id: String,
+        seq: u64,
+        root: FinalizedRoot,
+    ) -> StdResult<RootResponse> {
+        let cursor = ROOT_CURSORS.load(deps.storage, chain_id.clone())?;
+        let age = cursor.next_seq - seq;
+        Ok(RootResponse {
+            expired: root_expired(retention, &root, age, &env.block),
+            chain_id,
+            seq,
+            root,
+            age,
+        })
+    }
+
+    /// simulate_aggregation runs the vote extensions through the same decoding and
+    /// aggregation as `sudo`, reporting the tally of every chain instead of storing it.
+    pub fn simulate_aggregation(
+        deps: Deps,
+        env: Env,
+        data: Vec<GenericVE>,
+        total_power: Option<u64>,
+    ) -> StdResult<SimulateAggregationResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let to_std_err = |err: ContractError| match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        };
+        let decoded = decode_votes(deps, &env, &config, data).map_err(to_std_err)?;
+        let total_power = bonded_power(deps, &env, &config, total_power, decoded.received_power)?;
+        let paused = is_paused(deps.storage)?;
+
+        let mut response = SimulateAggregationResponse {
+            chains: vec![],
+            ignored_chains: vec![],
+            tally_overflow: vec![],
+            malformed_vote_extensions: decoded.malformed_events.len() as u64,
+            duplicate_operators: decoded.duplicate_operators,
+        };
+        for (chain_id, vote_extensions) in decoded.by_chain {
+            if paused {
+                response.ignored_chains.push(chain_id);
+                continue;
+            }
+            let aggregation = match aggregate_chain(
+                deps.storage,
+                &config,
+                env.block.height,
+                &chain_id,
+                &vote_extensions,
+                total_power,
+            ) {
+                Ok(Some(aggregation)) => aggregation,
+                Ok(None) => {
+                    response.ignored_chains.push(chain_id);
+                    continue;
+                }
+                Err(ContractError::TallyOverflow { chain_id }) => {
+                    response.tally_overflow.push(chain_id);
+                    continue;
+                }
+                Err(err) => return Err(to_std_err(err)),
+            };
+            let tally = aggregation.tally;
+            response.chains.push(ChainAggregationResponse {
+                chain_id,
+                roots: tally.ranked(),
+                participating_power: tally.participating_power,
+                total_power: tally.validator_set_power(),
+                winner: tally.leader().map(|(root, _)| root.clone()),
+                quorum_met: aggregation.finalized.is_some(),
+            });
+        }
+        Ok(response)
+    }
+
+    pub fn stake_of(deps: Deps, address: String) -> StdResult<StakeResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let stake = STAKE_MAP
+            .may_load(deps.storage, address.clone())?
+            .unwrap_or_default();
+        Ok(StakeResponse { address, stake })
+    }
+
+    /// list_stakers lists the addresses with coins staked by address.
+    pub fn list_stakers(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListStakersResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after
+            .map(|address| deps.api.addr_validate(&address))
+            .transpose()?
+            .map(Bound::exclusive);
+        let stakers = STAKE_MAP
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (address, stake) = item?;
+                Ok(StakeResponse { address, stake })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(ListStakersResponse { stakers })
+    }
+
+    pub fn total_stake(deps: Deps, env: Env) -> StdResult<TotalStakeResponse> {
+        Ok(TotalStakeResponse {
+            total_stake: TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default(),
+            bonded: bonded_stake(deps.storage, env.block.time)?,
+        })
+    }
+
+    fn chain_info_response(
+        deps: Deps,
+        config: &Config,
+        chain_id: String,
+        info: ChainInfo,
+    ) -> StdResult<ChainInfoResponse> {
+        let chain_config = CHAIN_CONFIGS
+            .may_load(deps.storage, chain_id.clone())?
+            .unwrap_or_default();
+        let retention = chain_config
+            .retention
+            .clone()
+            .unwrap_or_else(|| config.default_retention.clone());
+        Ok(ChainInfoResponse {
+            chain_id,
+            info,
+            config: chain_config,
+            retention,
+        })
+    }
+
+    pub fn submitters(deps: Deps, chain_id: String) -> StdResult<SubmittersResponse> {
+        Ok(SUBMITTERS
+            .may_load(deps.storage, chain_id)?
+            .map(|submitter_set| SubmittersResponse {
+                submitters: submitter_set.submitters,
+                threshold: submitter_set.threshold,
+            })
+            .unwrap_or(SubmittersResponse {
+                submitters: vec![],
+                threshold: 0,
+            }))
+    }
+
+    /// operator returns the keys, stake and jail term of an operator, resolving any
+    /// key rotation that has completed by now.
+    pub fn operator(deps: Deps, env: Env, address: String) -> StdResult<OperatorResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let operator = OPERATORS.may_load(deps.storage, address.clone())?;
+        let commission = operator
+            .as_ref()
+            .map_or(Decimal::zero(), |operator| operator.commission);
+        let (key, pending_key) = match operator {
+            Some(operator) => match operator.pending_key {
+                Some(pending) if pending.active_at <= env.block.time => (Some(pending.key), None),
+                pending_key => (Some(operator.key), pending_key),
+            },
+            None => (None, None),
+        };
+        Ok(OperatorResponse {
+            key,
+            pending_key,
+            stake: STAKE_MAP
+                .may_load(deps.storage, address.clone())?
+                .unwrap_or_default(),
+            delegated: DELEGATED
+                .may_load(deps.storage, address.clone())?
+                .unwrap_or_default(),
+            commission,
+            jailed_until: JAILED
+                .may_load(deps.storage, address.clone())?
+                .filter(|jailed_until| *jailed_until > env.block.time),
+            address,
+        })
+    }
+
+    pub fn signing_info(deps: Deps, operator: String) -> StdResult<SigningInfo> {
+        let operator = deps.api.addr_validate(&operator)?;
+        Ok(SIGNING_INFOS
+            .may_load(deps.storage, operator)?
+            .unwrap_or_default())
+    }
+
+    /// price returns the latest aggregated price of `pair`, it is an error if no price
+    /// was ever aggregated for it.
+    pub fn price(deps: Deps, pair: String) -> StdResult<PriceResponse> {
+        let info = PRICES
+            .may_load(deps.storage, pair.clone())?
+            .ok_or_else(|| StdError::not_found(format!("price of {pair}")))?;
+        Ok(PriceResponse {
+            pair,
+            price: info.price,
+            timestamp: info.timestamp,
+            height: info.height,
+            power: info.power,
+        })
+    }
+
+    pub fn prices(deps: Deps, pairs: Vec<String>) -> StdResult<PricesResponse> {
+        let prices = pairs
+            .into_iter()
+            .map(|pair| price(deps, pair))
+            .collect::<StdResult<_>>()?;
+        Ok(PricesResponse { prices })
+    }
+
+    pub fn pending_rewards(deps: Deps, address: String) -> StdResult<PendingRewardsResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let Some(rewards_config) = CONFIG.load(deps.storage)?.rewards else {
+            return Ok(PendingRewardsResponse {
+                rewards: vec![],
+                pool: vec![],
+            });
+        };
+        let coins = |amount: Uint128| {
+            (!amount.is_zero())
+                .then(|| Coin::new(amount.u128(), rewards_config.denom.clone()))
+                .into_iter()
+                .collect()
+        };
+        Ok(PendingRewardsResponse {
+            rewards: coins(rewards::pending(deps.storage, &address)?),
+            pool: coins(REWARD_POOL.may_load(deps.storage)?.unwrap_or_default()),
+        })
+    }
+
+    /// operator_delegations lists the delegations to an operator by delegator.
+    pub fn operator_delegations(
+        deps: Deps,
+        operator: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<DelegationsResponse> {
+        let operator = deps.api.addr_validate(&operator)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after
+            .map(|delegator| deps.api.addr_validate(&delegator))
+            .transpose()?
+            .map(Bound::exclusive);
+        let slash_factor = SLASH_FACTORS
+            .may_load(deps.storage, operator.clone())?
+            .unwrap_or_default();
+        let mut delegations = vec![];
+        for item in
+            DELEGATIONS
+                .prefix(operator.clone())
+                .range(deps.storage, start, None, Order::Ascending)
+        {
+            if delegations.len() == limit {
+                break;
+            }
+            let (delegator, amount) = item?;
+            let recorded = DELEGATION_SLASH_FACTORS
+                .may_load(deps.storage, (operator.clone(), delegator.clone()))?
+                .unwrap_or_default();
+            // delegations slashed away entirely are only removed once they are touched
+            let amount = slash_factor.scale(&recorded, amount);
+            if !amount.is_empty() {
+                delegations.push(Delegation {
+                    delegator,
+                    operator: operator.clone(),
+                    amount,
+                });
+            }
+        }
+        Ok(DelegationsResponse { delegations })
+    }
+
+    /// delegator_delegations lists the delegations of a delegator by operator.
+    pub fn delegator_delegations(
+        deps: Deps,
+        delegator: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<DelegationsResponse> {
+        let delegator = deps.api.addr_validate(&delegator)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after
+            .map(|operator| deps.api.addr_validate(&operator))
+            .transpose()?
+            .map(Bound::exclusive);
+        let mut delegations = vec![];
+        for operator in DELEGATOR_OPERATORS.prefix(delegator.clone()).keys(
+            deps.storage,
+            start,
+            None,
+            Order::Ascending,
+        ) {
+            if delegations.len() == limit {
+                break;
+            }
+            let operator = operator?;
+            // delegators whose delegation is fully undelegating are listed without one
+            if let Some(amount) =
+                delegation(deps.storage, &operator, &delegator)?.filter(|amount| !amount.is_empty())
+            {
+                delegations.push(Delegation {
+                    delegator: delegator.clone(),
+                    operator,
+                    amount,
+                });
+            }
+        }
+        Ok(DelegationsResponse { delegations })
+    }
+
+    pub fn pending_unbondings(deps: Deps, address: String) -> StdResult<PendingUnbondingsResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let mut undelegations = vec![];
+        for operator in DELEGATOR_OPERATORS.prefix(address.clone()).keys(
+            deps.storage,
+            None,
+            None,
+            Order::Ascending,
+        ) {
+            let operator = operator?;
+            if let Some(unbondings) = super::undelegations(deps.storage, &operator, &address)?
+                .filter(|unbondings| !unbondings.is_empty())
+            {
+                undelegations.push(PendingUndelegation {
+                    operator,
+                    unbondings,
+                });
+            }
+        }
+        Ok(PendingUnbondingsResponse {
+            unbondings: UNBONDING_QUEUE
+                .may_load(deps.storage, address)?
+                .unwrap_or_default(),
+            undelegations,
+        })
+    }
+
+    /// lookup_hash returns the age of a root of the chain, unless the root has expired
+    /// under the retention of the chain.
+    pub fn lookup_hash(
+        deps: Deps,
+        env: Env,
+        chain_id: String,
+        hash: Binary,
+    ) -> StdResult<LookupHashResponse> {
+        let root = lookup_root(deps, env, chain_id, hash)?;
+        Ok(LookupHashResponse { age: root.age })
+    }
+
+    /// lookup_root returns the metadata of a root of the chain, unless the root has
+    /// expired under the retention of the chain.
+    pub fn lookup_root(
+        deps: Deps,
+        env: Env,
+        chain_id: String,
+        hash: Binary,
+    ) -> StdResult<LookupRootResponse> {
+        let seq = ROOT_INDEX
+            .may_load(deps.storage, (chain_id.clone(), hash.to_vec()))?
+            .ok_or_else(|| StdError::not_found("HashNotFound".to_string()))?;
+        let root = ROOTS.load(deps.storage, (chain_id.clone(), seq))?;
+        let cursor = ROOT_CURSORS.load(deps.storage, chain_id.clone())?;
+        let age = cursor.next_seq - seq;
+        let retention = chain_retention(deps.storage, &CONFIG.load(deps.storage)?, &chain_id)?;
+        if root_expired(&retention, &root, age, &env.block) {
+            return Err(StdError::generic_err("RootExpired"));
+        }
+        Ok(LookupRootResponse {
+            age,
+            source_height: root.source_height,
+            finalized_height: root.finalized_height,
+            finalized_at: root.finalized_at,
+            power: root.power,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregation::SupermajorityPlurality;
+    use crate::msg::{
+        ChainInfoResponse, ConfigUpdate, GenericVE, ListChainsResponse, PendingRoot,
+        PendingUnbondingsResponse, PendingVotesResponse,
+    };
+    use crate::state::{
+        ChainConfig, ChainFamily, HashFunction, KeyType, RootKind, ROOT_SUBMISSIONS,
+    };
+    use bincode::serialize;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, coins, from_json, Attribute, BankMsg, Decimal};
+
+    fn source_root(root: &str) -> SourceRoot {
+        SourceRoot {
+            root: Binary::from(root.as_bytes()),
+            height: 1,
+        }
+    }
+
+    /// generic_ve builds the vote extension of `operator` voting for `roots` of each
+    /// chain and `prices` of each pair.
+    fn generic_ve(
+        roots: &[(&str, SourceRoot)],
+        prices: &[(&str, u128)],
+        ve_power: u64,
+        operator: Option<&str>,
+    ) -> GenericVE {
+        let vote = Vote {
+            roots: roots
+                .iter()
+                .map(|(chain_id, root)| (chain_id.to_string(), root.clone()))
+                .collect(),
+            prices: prices
+                .iter()
+                .map(|(pair, price)| (pair.to_string(), Uint128::new(*price)))
+                .collect(),
+        };
+        GenericVE {
+            vote: Binary(serialize(&vote).unwrap()),
+            ve_power,
+            operator: operator.map(str::to_string),
+        }
+    }
+
+    fn register_chain(deps: DepsMut, chain_id: &str) {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RegisterChain {
+                chain_id: chain_id.to_string(),
+                family: ChainFamily::Cosmos,
+                caip2: format!("cosmos:{chain_id}"),
+                hash_function: HashFunction::Sha256,
+                root_kind: RootKind::AppHash,
+                retention: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn register_operator(deps: DepsMut, operator: &str) {
+        let msg = ExecuteMsg::RegisterOperator {
+            pubkey: Binary::from([0x02; 33].as_slice()),
+            key_type: KeyType::Secp256k1,
+        };
+        execute(deps, mock_env(), mock_info(operator, &[]), msg).unwrap();
+    }
+
+    fn set_submitters(
+        deps: DepsMut,
+        chain_id: &str,
+        submitters: &[&str],
+        threshold: u64,
+    ) -> ContractResult<Response> {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetChainSubmitters {
+                chain_id: chain_id.to_string(),
+                submitters: submitters.iter().map(|s| s.to_string()).collect(),
+                threshold,
+            },
+        )
+    }
+
+    fn default_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            owner: None,
+            unbonding_period: 60,
+            power_source: PowerSource::VotePower,
+            denom_weights: BTreeMap::new(),
+            default_threshold: Decimal::from_ratio(2u128, 3u128),
+        }
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let msg = default_instantiate_msg();
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn do_some_hash_stuff() {
+        let mut deps = mock_dependencies();
+
+        let msg = default_instantiate_msg();
+        let info = mock_info("creator", &coins(2, "token"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let test_case_msg = SudoMsg {
+            data: vec![],
+            total_power: None,
+        };
+        assert!(Ok(Response::new()).eq(&sudo(deps.as_mut(), mock_env(), test_case_msg)));
+
+        // let bin = Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg==").unwrap();
+
+        let mut map_thing = BTreeMap::<String, SourceRoot>::new();
+        map_thing.insert(
+            "foo".to_string(),
+            SourceRoot {
+                root: Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg").unwrap(),
+                height: 1,
+            },
+        );
+        let second_case = SudoMsg {
+            data: vec![generic_ve(
+                &[("foo", map_thing["foo"].clone())],
+                &[],
+                1000,
+                None,
+            )],
+            total_power: None,
+        };
+
+        let res = sudo(deps.as_mut(), mock_env(), second_case).unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("root_finalized")
+                .add_attribute("chain_id", "foo")
+                .add_attribute("root", hex::encode(&map_thing["foo"].root))
+                .add_attribute("source_height", "1")
+                .add_attribute("seq", "0")
+                .add_attribute("power", "1000")
+                .add_attribute("participating_power", "1000")
+                .add_attribute("total_power", "1000")
+                .add_attribute("participation", "1")]
+        );
+
+        assert!(query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::LookupHash {
+                chain_id: "foo".to_string(),
+                hash: Binary::from_base64("eyJyb290cyI6eyJmb28iOiJZbUZ5In19Cg").unwrap()
+            }
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn unbond_and_claim() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        let msg = default_instantiate_msg();
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let info = mock_info("operator", &coins(100, "stake"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+
+        // cannot unbond more than is staked
+        let info = mock_info("operator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Unbond {
+                amount: coins(101, "stake"),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientStake {
+                denom: "stake".to_string()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Unbond {
+                amount: coins(40, "stake"),
+            },
+        )
+        .unwrap();
+        let staked = STAKE_MAP.load(&deps.storage, info.sender.clone()).unwrap();
+        assert_eq!(staked, vec![coin(60, "stake")]);
+
+        let pending: PendingUnbondingsResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::PendingUnbondings {
+                    address: "operator".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pending.unbondings.len(), 1);
+        assert_eq!(
+            pending.unbondings[0].release_at,
+            env.block.time.plus_seconds(60)
+        );
+
+        // nothing is claimable before the unbonding period elapses
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::ClaimUnbonded {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NothingToClaim {});
+
+        env.block.time = env.block.time.plus_seconds(60);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimUnbonded {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            BankMsg::Send {
+                to_address: "operator".to_string(),
+                amount: coins(40, "stake"),
+            }
+            .into()
+        );
+    }
+
+    #[test]
+    fn stake_weighted_power() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([
+            ("stake".to_string(), Decimal::one()),
+            ("lst".to_string(), Decimal::percent(50)),
+        ]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        // "honest" has 100 stake + 100 lst = 150 power, "whale" claims a huge
+        // ve_power but only holds an unweighted denom and so carries no power.
+        // "lurker" holds weighted stake but never registered as an operator.
+        let info = mock_info("honest", &[coin(100, "stake"), coin(100, "lst")]);
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        let info = mock_info("whale", &coins(1_000_000, "other"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        let info = mock_info("lurker", &coins(1_000, "stake"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        for operator in ["honest", "whale"] {
+            register_operator(deps.as_mut(), operator);
+        }
+
+        let vote = |root: &str, ve_power: u64, operator: Option<&str>| {
+            generic_ve(&[("foo", source_root(root))], &[], ve_power, operator)
+        };
+        let msg = SudoMsg {
+            data: vec![
+                vote("honest_root", 1, Some("honest")),
+                vote("forged_root", 1_000_000, Some("whale")),
+                vote("forged_root", 1_000_000, Some("lurker")),
+                vote("forged_root", 1_000_000, None),
+            ],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+
+        assert!(query::lookup_hash(
+            deps.as_ref(),
+            mock_env(),
+            "foo".to_string(),
+            Binary::from("honest_root".as_bytes())
+        )
+        .is_ok());
+        assert!(query::lookup_hash(
+            deps.as_ref(),
+            mock_env(),
+            "foo".to_string(),
+            Binary::from("forged_root".as_bytes())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn bonded_power_only_counts_active_operators() {
+        use crate::msg::TotalStakeResponse;
+        use crate::state::{BONDED, JAIL_RELEASES};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let msg = ExecuteMsg::UpdateConfig(ConfigUpdate {
+            default_strategy: Some(Strategy::WeightedThreshold),
+            ..Default::default()
+        });
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        for (staker, amount) in [("op_a", 100), ("op_b", 100), ("lurker", 1000)] {
+            let info = mock_info(staker, &coins(amount, "stake"));
+            execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        }
+        for operator in ["op_a", "op_b"] {
+            register_operator(deps.as_mut(), operator);
+        }
+        let total_stake = |deps: Deps, env: &Env| -> TotalStakeResponse {
+            from_json(query(deps, env.clone(), QueryMsg::TotalStake {}).unwrap()).unwrap()
+        };
+        assert_eq!(
+            total_stake(deps.as_ref(), &env),
+            TotalStakeResponse {
+                total_stake: coins(1200, "stake"),
+                bonded: coins(200, "stake"),
+            }
+        );
+
+        // neither the jailed operator nor stake delegated to it while jailed counts
+        let op_b = Addr::unchecked("op_b");
+        let released_at = env.block.time.plus_seconds(100);
+        execute::jail(deps.as_mut().storage, &op_b, released_at).unwrap();
+        let info = mock_info("alice", &coins(50, "stake"));
+        let msg = ExecuteMsg::Delegate {
+            operator: "op_b".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(total_stake(deps.as_ref(), &env).bonded, coins(100, "stake"));
+
+        // "op_a" alone reaches quorum, the stake of "lurker" cannot vote anyway
+        let msg = SudoMsg {
+            data: vec![generic_ve(
+                &[("foo", source_root("root"))],
+                &[],
+                1,
+                Some("op_a"),
+            )],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        let root = Binary::from("root".as_bytes());
+        assert!(query::lookup_hash(deps.as_ref(), env.clone(), "foo".to_string(), root).is_ok());
+
+        // the stake of "op_b" counts again once its jail term is over
+        env.block.time = released_at;
+        assert_eq!(total_stake(deps.as_ref(), &env).bonded, coins(250, "stake"));
+        let msg = SudoMsg {
+            data: vec![],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(BONDED.load(&deps.storage).unwrap(), coins(250, "stake"));
+        assert!(!JAIL_RELEASES.has(&deps.storage, (released_at.nanos(), op_b)));
+
+        // stake held before registering counts once the staker registers
+        register_operator(deps.as_mut(), "lurker");
+        assert_eq!(
+            total_stake(deps.as_ref(), &env).bonded,
+            coins(1250, "stake")
+        );
+    }
+
+    #[test]
+    fn repeated_operator_votes_once() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+        for staker in ["alice", "bob"] {
+            let info = mock_info(staker, &coins(100, "stake"));
+            execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+            register_operator(deps.as_mut(), staker);
+        }
+
+        let vote = |operator: &str, root: &str| {
+            generic_ve(&[("foo", source_root(root))], &[], 1, Some(operator))
+        };
+        // counted three times "alice" would hold 300 of 400 power
+        let msg = SudoMsg {
+            data: vec![
+                vote("alice", "a"),
+                vote("alice", "a"),
+                vote("bob", "b"),
+                vote("alice", "a"),
+            ],
+            total_power: None,
+        };
+        let res = sudo(deps.as_mut(), env, msg).unwrap();
+        assert_eq!(
+            res.attributes
+                .iter()
+                .filter(|attr| attr.key == "duplicate_operator")
+                .map(|attr| attr.value.as_str())
+                .collect::<Vec<_>>(),
+            vec!["alice", "alice"]
+        );
+        assert!(query::lookup_hash(
+            deps.as_ref(),
+            mock_env(),
+            "foo".to_string(),
+            source_root("a").root
+        )
+        .is_err());
+        let quorum = res
+            .events
+            .iter()
+            .find(|event| event.ty == "quorum_not_reached")
+            .unwrap();
+        assert!(quorum
+            .attributes
+            .contains(&Attribute::new("participating_power", "200")));
+    }
+
+    #[test]
+    fn chain_threshold_overrides() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        register_chain(deps.as_mut(), "bar");
+
+        let set_threshold = ExecuteMsg::SetChainThreshold {
+            chain_id: "foo".to_string(),
+            threshold: Some(Decimal::percent(90)),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            set_threshold.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            set_threshold,
+        )
+        .unwrap();
+
+        let msg = QueryMsg::ChainInfo {
+            chain_id: "foo".to_string(),
+        };
+        let info: ChainInfoResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(
+            info.config,
+            ChainConfig {
+                threshold: Some(Decimal::percent(90)),
+                strategy: None,
+                vote_window: None,
+                retention: None,
+            }
+        );
+
+        // 80% agreement clears the default 2/3 threshold on "bar" but not the
+        // 90% override on "foo"
+        let vote = |root: &str, ve_power: u64| {
+            let roots = [("foo", source_root(root)), ("bar", source_root(root))];
+            generic_ve(&roots, &[], ve_power, None)
+        };
+        let msg = SudoMsg {
+            data: vec![vote("majority", 80), vote("minority", 20)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env, msg).unwrap();
+
+        let majority = Binary::from("majority".as_bytes());
+        assert!(query::lookup_hash(
+            deps.as_ref(),
+            mock_env(),
+            "bar".to_string(),
+            majority.clone()
+        )
+        .is_ok());
+        assert!(
+            query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), majority).is_err()
+        );
+    }
+
+    fn vote_extension(chain_id: &str, root: &str, ve_power: Uint128) -> VoteExtension {
+        VoteExtension {
+            vote: Vote {
+                roots: BTreeMap::from([(chain_id.to_string(), source_root(root))]),
+                prices: BTreeMap::new(),
+            },
+            ve_power,
+            operator: None,
+        }
+    }
+
+    #[test]
+    fn aggregate_ves_threshold_is_exact() {
+        let two_thirds = Decimal::from_ratio(2u128, 3u128);
+        let votes = vec![
+            vote_extension("foo", "a", Uint128::new(2)),
+            vote_extension("foo", "b", Uint128::new(1)),
+        ];
+        assert_eq!(
+            aggregate_ves(
+                "foo".to_string(),
+                &votes,
+                Uint128::new(3),
+                &SupermajorityPlurality,
+                two_thirds
+            )
+            .map(|(finalized, _)| finalized),
+            Ok(Some((source_root("a"), Uint128::new(2))))
+        );
+        assert_eq!(
+            aggregate_ves(
+                "foo".to_string(),
+                &votes,
+                Uint128::new(3),
+                &SupermajorityPlurality,
+                Decimal::percent(67)
+            )
+            .map(|(finalized, _)| finalized),
+            Ok(None)
+        );
+        assert_eq!(
+            aggregate_ves(
+                "foo".to_string(),
+                &[],
+                Uint128::zero(),
+                &SupermajorityPlurality,
+                two_thirds
+            )
+            .map(|(finalized, _)| finalized),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn aggregate_ves_overflow_is_an_error() {
+        let votes = vec![
+            vote_extension("foo", "a", Uint128::MAX),
+            vote_extension("foo", "b", Uint128::one()),
+        ];
+        assert_eq!(
+            aggregate_ves(
+                "foo".to_string(),
+                &votes,
+                Uint128::MAX,
+                &SupermajorityPlurality,
+                Decimal::percent(50)
+            ),
+            Err(ContractError::TallyOverflow {
+                chain_id: "foo".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_vote_extensions_are_skipped() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let msg = SudoMsg {
+            data: vec![
+                generic_ve(&[("foo", source_root("root"))], &[], 100, None),
+                GenericVE {
+                    vote: Binary::from(&[0xff; 3][..]),
+                    ve_power: 40,
+                    operator: Some("faulty".to_string()),
+                },
+            ],
+            total_power: None,
+        };
+        let res = sudo(deps.as_mut(), env, msg).unwrap();
+
+        assert!(query::lookup_hash(
+            deps.as_ref(),
+            mock_env(),
+            "foo".to_string(),
+            Binary::from("root".as_bytes())
+        )
+        .is_ok());
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("malformed_vote_extensions", "1")));
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("malformed_power", "40")));
+        let malformed: Vec<&Event> = res
+            .events
+            .iter()
+            .filter(|event| event.ty == "malformed_vote_extension")
+            .collect();
+        assert_eq!(malformed.len(), 1);
+        assert_eq!(
+            malformed[0].attributes[..2],
+            [
+                Attribute::new("operator", "faulty"),
+                Attribute::new("power", "40")
+            ]
+        );
+    }
+
+    #[test]
+    fn first_to_quorum_carries_votes_over_blocks() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetChainStrategy {
+                chain_id: "foo".to_string(),
+                strategy: Some(Strategy::FirstToQuorum { window: 2 }),
+            },
+        )
+        .unwrap();
+
+        // every operator submits a vote extension each block, but only some of them
+        // have observed the root of "foo" yet
+        let vote = |chain_id: Option<&str>, operator: &str, ve_power: u64| {
+            let roots: Vec<_> = chain_id
+                .map(|chain_id| (chain_id, source_root("root")))
+                .into_iter()
+                .collect();
+            generic_ve(&roots, &[], ve_power, Some(operator))
+        };
+        let root_found = |deps: Deps| {
+            query::lookup_hash(
+                deps,
+                mock_env(),
+                "foo".to_string(),
+                Binary::from("root".as_bytes()),
+            )
+            .is_ok()
+        };
+        let pending_operators = |deps: Deps| {
+            PENDING_VOTES
+                .prefix("foo".to_string())
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()
+                .unwrap()
+        };
+
+        // block 1: 40% of the power has seen the root
+        let msg = SudoMsg {
+            data: vec![
+                vote(Some("foo"), "alice", 40),
+                vote(None, "bob", 30),
+                vote(None, "carol", 30),
+            ],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(!root_found(deps.as_ref()));
+        assert_eq!(pending_operators(deps.as_ref()), vec!["alice".to_string()]);
+
+        // block 2: alice's vote is carried over, bob catches up, 70% in total
+        env.block.height += 1;
+        let msg = SudoMsg {
+            data: vec![
+                vote(None, "alice", 40),
+                vote(Some("foo"), "bob", 30),
+                vote(None, "carol", 30),
+            ],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(root_found(deps.as_ref()));
+        assert!(pending_operators(deps.as_ref()).is_empty());
+
+        // votes older than the window are pruned instead of counting
+        let msg = SudoMsg {
+            data: vec![vote(Some("foo"), "alice", 40), vote(None, "bob", 60)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        env.block.height += 2;
+        let msg = SudoMsg {
+            data: vec![vote(None, "alice", 40), vote(Some("foo"), "bob", 30)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env, msg).unwrap();
+        assert_eq!(pending_operators(deps.as_ref()), vec!["bob".to_string()]);
+    }
+
+    #[test]
+    fn vote_window_accumulates_plurality_votes() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetChainVoteWindow {
+                chain_id: "foo".to_string(),
+                vote_window: Some(0),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidVoteWindow {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetChainVoteWindow {
+                chain_id: "foo".to_string(),
+                vote_window: Some(3),
+            },
+        )
+        .unwrap();
+
+        let vote = |root: &str, operator: &str, power: u64| {
+            generic_ve(&[("foo", source_root(root))], &[], power, Some(operator))
+        };
+        let pending = |deps: Deps, env: &Env| -> PendingVotesResponse {
+            from_json(
+                query(
+                    deps,
+                    env.clone(),
+                    QueryMsg::PendingVotes {
+                        chain_id: "foo".to_string(),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        // a split vote does not finalize, but is kept for the next blocks
+        let msg = SudoMsg {
+            data: vec![vote("a", "alice", 40), vote("b", "bob", 30)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            pending(deps.as_ref(), &env).roots,
+            vec![
+                PendingRoot {
+                    root: source_root("a"),
+                    power: Uint128::new(40),
+                    operators: vec!["alice".to_string()],
+                },
+                PendingRoot {
+                    root: source_root("b"),
+                    power: Uint128::new(30),
+                    operators: vec!["bob".to_string()],
+                },
+            ]
+        );
+
+        // two blocks later, carol's vote brings "a" to 2/3 of the accumulated power
+        env.block.height += 2;
+        let msg = SudoMsg {
+            data: vec![vote("a", "carol", 20)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        let root = Binary::from("a".as_bytes());
+        assert!(query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), root).is_ok());
+        assert!(pending(deps.as_ref(), &env).roots.is_empty());
+
+        // pending votes expire even if the chain receives no further votes
+        let msg = SudoMsg {
+            data: vec![vote("c", "alice", 40), vote("d", "bob", 40)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(pending(deps.as_ref(), &env).roots.len(), 2);
+        env.block.height += 3;
+        let msg = SudoMsg {
+            data: vec![],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(pending(deps.as_ref(), &env).roots.is_empty());
+
+        // and are pruned the next time the chain is voted for
+        let msg = SudoMsg {
+            data: vec![vote("e", "carol", 20), vote("f", "dave", 20)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        let stored: Vec<String> = PENDING_VOTES
+            .prefix("foo".to_string())
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(stored, vec!["carol".to_string(), "dave".to_string()]);
+        assert!(OPERATOR_PENDING_VOTES
+            .prefix("alice".to_string())
+            .is_empty(deps.as_ref().storage));
+    }
+
+    #[test]
+    fn lookup_root_returns_root_metadata() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        set_submitters(deps.as_mut(), "foo", &["creator"], 1).unwrap();
+
+        let root = SourceRoot {
+            root: Binary::from("root".as_bytes()),
+            height: 42,
+        };
+        let vote = |ve_power: u64| generic_ve(&[("foo", root.clone())], &[], ve_power, None);
+        let msg = SudoMsg {
+            data: vec![vote(60), vote(30)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+
+        let lookup = |deps: Deps| -> LookupRootResponse {
+            from_json(
+                query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::LookupRoot {
+                        chain_id: "foo".to_string(),
+                        hash: Binary::from("root".as_bytes()),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            lookup(deps.as_ref()),
+            LookupRootResponse {
+                age: 1,
+                source_height: 42,
+                finalized_height: env.block.height,
+                finalized_at: env.block.time,
+                power: Uint128::new(90),
+            }
+        );
+
+        // a later root does not change the metadata of the earlier one
+        env.block.height += 1;
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::SubmitRoot {
+                chain_id: "foo".to_string(),
+                root: Binary::from("next".as_bytes()),
+                height: 43,
+            },
+        )
+        .unwrap();
+        let response = lookup(deps.as_ref());
+        assert_eq!(response.age, 2);
+        assert_eq!(response.source_height, 42);
+        assert_eq!(response.power, Uint128::new(90));
+
+        // LookupHash keeps answering with the age alone
+        let response: LookupHashResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::LookupHash {
+                    chain_id: "foo".to_string(),
+                    hash: Binary::from("root".as_bytes()),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response, LookupHashResponse { age: 2 });
+    }
+
+    #[test]
+    fn roots_are_evicted_oldest_first() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        set_submitters(deps.as_mut(), "foo", &["creator"], 1).unwrap();
+        let root = |index: u64| Binary::from(format!("root{index}").as_bytes());
+        let submit = |deps: DepsMut, index: u64| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("creator", &[]),
+                ExecuteMsg::SubmitRoot {
+                    chain_id: "foo".to_string(),
+                    root: root(index),
+                    height: index,
+                },
+            )
+            .unwrap();
+        };
+
+        for index in 0..=CACHE_SIZE {
+            submit(deps.as_mut(), index);
+        }
+        // resubmitting a known root neither moves nor duplicates it
+        submit(deps.as_mut(), CACHE_SIZE);
+
+        assert!(query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), root(0)).is_err());
+        for index in 1..=CACHE_SIZE {
+            let response =
+                query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), root(index));
+            assert_eq!(response.unwrap().age, CACHE_SIZE + 1 - index);
+        }
+        let stored = ROOTS
+            .prefix("foo".to_string())
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(stored, (1..=CACHE_SIZE).collect::<Vec<_>>());
+        assert!(query::lookup_hash(deps.as_ref(), mock_env(), "bar".to_string(), root(1)).is_err());
+    }
+
+    #[test]
+    fn chain_retention_expires_roots() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        set_submitters(deps.as_mut(), "foo", &["creator"], 1).unwrap();
+        let set_retention = |deps: DepsMut, retention: Retention| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("creator", &[]),
+                ExecuteMsg::SetChainRetention {
+                    chain_id: "foo".to_string(),
+                    retention: Some(retention),
+                },
+            )
+        };
+        let err = set_retention(
+            deps.as_mut(),
+            Retention {
+                max_roots: 0,
+                max_age_seconds: None,
+                max_age_blocks: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidRetention {});
+        set_retention(
+            deps.as_mut(),
+            Retention {
+                max_roots: 2,
+                max_age_seconds: None,
+                max_age_blocks: Some(10),
+            },
+        )
+        .unwrap();
+
+        let root = |index: u64| Binary::from(format!("root{index}").as_bytes());
+        for index in 0..3 {
+            env.block.height += 1;
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("creator", &[]),
+                ExecuteMsg::SubmitRoot {
+                    chain_id: "foo".to_string(),
+                    root: root(index),
+                    height: index,
+                },
+            )
+            .unwrap();
+        }
+        let lookup = |deps: Deps, env: &Env, index: u64| {
+            query::lookup_hash(deps, env.clone(), "foo".to_string(), root(index))
+        };
+        assert!(lookup(deps.as_ref(), &env, 0).is_err());
+        assert!(lookup(deps.as_ref(), &env, 1).is_ok());
+        assert!(lookup(deps.as_ref(), &env, 2).is_ok());
+
+        // lowering max_roots takes effect before the excess roots are evicted
+        set_retention(
+            deps.as_mut(),
+            Retention {
+                max_roots: 1,
+                max_age_seconds: None,
+                max_age_blocks: Some(10),
+            },
+        )
+        .unwrap();
+        assert!(lookup(deps.as_ref(), &env, 1).is_err());
+        assert!(lookup(deps.as_ref(), &env, 2).is_ok());
+
+        // root 2 was finalized at the current height and expires 10 blocks later
+        env.block.height += 10;
+        assert!(lookup(deps.as_ref(), &env, 2).is_ok());
+        env.block.height += 1;
+        assert_eq!(
+            lookup(deps.as_ref(), &env, 2).unwrap_err(),
+            StdError::generic_err("RootExpired")
+        );
+
+        // submitting an expired root again finalizes it anew
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SubmitRoot {
+                chain_id: "foo".to_string(),
+                root: root(2),
+                height: 2,
+            },
+        )
+        .unwrap();
+        let root_hex = hex::encode(root(2));
+        assert_eq!(
+            res.events,
+            vec![
+                Event::new("root_evicted")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", &root_hex)
+                    .add_attribute("seq", "2"),
+                Event::new("root_finalized")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", &root_hex)
+                    .add_attribute("source_height", "2")
+                    .add_attribute("seq", "3")
+                    .add_attribute("power", "0"),
+                Event::new("root_evicted")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", hex::encode(root(1)))
+                    .add_attribute("seq", "1"),
+            ]
+        );
+        let found = query::lookup_root(deps.as_ref(), env.clone(), "foo".to_string(), root(2));
+        assert_eq!(found.unwrap().finalized_height, env.block.height);
+        env.block.height += 10;
+        assert!(lookup(deps.as_ref(), &env, 2).is_ok());
+    }
+
+    #[test]
+    fn submitted_roots_need_threshold_confirmations() {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let submit = |deps: DepsMut, sender: &str, root: &str| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::SubmitRoot {
+                    chain_id: "foo".to_string(),
+                    root: Binary::from(root.as_bytes()),
+                    height: 1,
+                },
+            )
+        };
+        let found = |deps: Deps, root: &str| {
+            query::lookup_hash(
+                deps,
+                mock_env(),
+                "foo".to_string(),
+                Binary::from(root.as_bytes()),
+            )
+            .is_ok()
+        };
+
+        // submitting is disabled until the admin sets submitters for the chain
+        let err = submit(deps.as_mut(), "creator", "root").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let err = set_submitters(deps.as_mut(), "foo", &["alice", "bob", "carol"], 4).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSubmitterThreshold {});
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::SetChainSubmitters {
+                chain_id: "foo".to_string(),
+                submitters: vec!["alice".to_string()],
+                threshold: 1,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        set_submitters(deps.as_mut(), "foo", &["alice", "bob", "carol"], 2).unwrap();
+
+        let err = submit(deps.as_mut(), "mallory", "forged").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        submit(deps.as_mut(), "alice", "root").unwrap();
+        let err = submit(deps.as_mut(), "alice", "root").unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DuplicateSubmission {
+                submitter: "alice".to_string()
+            }
+        );
+        assert!(!found(deps.as_ref(), "root"));
+        submit(deps.as_mut(), "bob", "root").unwrap();
+        assert!(found(deps.as_ref(), "root"));
+
+        // confirmations of removed submitters no longer count
+        submit(deps.as_mut(), "alice", "next").unwrap();
+        set_submitters(deps.as_mut(), "foo", &["bob", "carol"], 2).unwrap();
+        submit(deps.as_mut(), "bob", "next").unwrap();
+        assert!(!found(deps.as_ref(), "next"));
+        submit(deps.as_mut(), "carol", "next").unwrap();
+        assert!(found(deps.as_ref(), "next"));
+
+        // an empty list disables submitting again
+        set_submitters(deps.as_mut(), "foo", &[], 0).unwrap();
+        let err = submit(deps.as_mut(), "bob", "other").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn stale_submissions_are_pruned() {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        set_submitters(deps.as_mut(), "foo", &["alice", "bob"], 2).unwrap();
+        let submit = |deps: DepsMut, sender: &str, root: &str, height: u64| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::SubmitRoot {
+                    chain_id: "foo".to_string(),
+                    root: Binary::from(root.as_bytes()),
+                    height,
+                },
+            )
+        };
+        let submissions = |deps: Deps| -> Vec<(Vec<u8>, u64)> {
+            ROOT_SUBMISSIONS
+                .sub_prefix("foo".to_string())
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()
+                .unwrap()
+        };
+
+        // a root that is never confirmed
+        submit(deps.as_mut(), "alice", "orphan", 5).unwrap();
+        submit(deps.as_mut(), "alice", "root", 10).unwrap();
+        submit(deps.as_mut(), "bob", "root", 10).unwrap();
+        assert_eq!(submissions(deps.as_ref()), vec![(b"orphan".to_vec(), 5)]);
+
+        let err = submit(deps.as_mut(), "bob", "orphan", 5).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::StaleRoot {
+                chain_id: "foo".to_string(),
+                height: 5
+            }
+        );
+        // the next submission drops the ones below the oldest kept root
+        submit(deps.as_mut(), "alice", "next", 11).unwrap();
+        assert_eq!(submissions(deps.as_ref()), vec![(b"next".to_vec(), 11)]);
+    }
+
+    #[test]
+    fn chain_registry() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        let register = |deps: DepsMut, sender: &str, chain_id: &str, caip2: &str| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::RegisterChain {
+                    chain_id: chain_id.to_string(),
+                    family: ChainFamily::Evm,
+                    caip2: caip2.to_string(),
+                    hash_function: HashFunction::Keccak256,
+                    root_kind: RootKind::ReceiptsRoot,
+                    retention: Some(Retention {
+                        max_roots: 100,
+                        max_age_seconds: Some(3600),
+                        max_age_blocks: None,
+                    }),
+                },
+            )
+        };
+        let admin = |deps: DepsMut, msg: ExecuteMsg| {
+            execute(deps, mock_env(), mock_info("creator", &[]), msg)
+        };
+        let vote = |chain_ids: &[&str]| SudoMsg {
+            data: vec![generic_ve(
+                &chain_ids
+                    .iter()
+                    .map(|chain_id| (*chain_id, source_root("root")))
+                    .collect::<Vec<_>>(),
+                &[],
+                10,
+                None,
+            )],
+            total_power: None,
+        };
+        let found = |deps: Deps, chain_id: &str| {
+            query::lookup_hash(
+                deps,
+                mock_env(),
+                chain_id.to_string(),
+                Binary::from("root".as_bytes()),
+            )
+            .is_ok()
+        };
+
+        assert_eq!(
+            register(deps.as_mut(), "anyone", "eth", "eip155:1").unwrap_err(),
+            ContractError::Unauthorized {}
+        );
+        assert_eq!(
+            register(deps.as_mut(), "creator", "eth", "eip155").unwrap_err(),
+            ContractError::InvalidCaip2 {
+                caip2: "eip155".to_string()
+            }
+        );
+        register(deps.as_mut(), "creator", "eth", "eip155:1").unwrap();
+        assert_eq!(
+            register(deps.as_mut(), "creator", "eth", "eip155:1").unwrap_err(),
+            ContractError::ChainAlreadyRegistered {
+                chain_id: "eth".to_string()
+            }
+        );
+        register(deps.as_mut(), "creator", "arb", "eip155:42161").unwrap();
+        assert_eq!(
+            admin(
+                deps.as_mut(),
+                ExecuteMsg::SetChainThreshold {
+                    chain_id: "unknown".to_string(),
+                    threshold: None,
+                },
+            )
+            .unwrap_err(),
+            ContractError::ChainNotRegistered {
+                chain_id: "unknown".to_string()
+            }
+        );
+
+        let info: ChainInfoResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::ChainInfo {
+                    chain_id: "eth".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(info.info.caip2, "eip155:1");
+        assert!(!info.info.paused);
+        assert_eq!(info.retention.max_roots, 100);
+        let list = |deps: Deps, start_after: Option<&str>, limit: Option<u32>| {
+            let response: ListChainsResponse = from_json(
+                query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::ListChains {
+                        start_after: start_after.map(str::to_string),
+                        limit,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            response
+                .chains
+                .into_iter()
+                .map(|chain| chain.chain_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(list(deps.as_ref(), None, None), vec!["arb", "eth"]);
+        assert_eq!(list(deps.as_ref(), None, Some(1)), vec!["arb"]);
+        assert_eq!(list(deps.as_ref(), Some("arb"), None), vec!["eth"]);
+
+        // votes for unregistered or paused chains are ignored
+        admin(
+            deps.as_mut(),
+            ExecuteMsg::SetChainPaused {
+                chain_id: "arb".to_string(),
+                paused: true,
+            },
+        )
+        .unwrap();
+        let res = sudo(deps.as_mut(), env.clone(), vote(&["eth", "arb", "unknown"])).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                Attribute::new("ignored_chain", "arb"),
+                Attribute::new("ignored_chain", "unknown"),
+            ]
+        );
+        assert!(found(deps.as_ref(), "eth"));
+        assert!(!found(deps.as_ref(), "arb"));
+        assert!(!found(deps.as_ref(), "unknown"));
+
+        // deregistering drops the roots, registering again starts from scratch
+        admin(
+            deps.as_mut(),
+            ExecuteMsg::DeregisterChain {
+                chain_id: "eth".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(!found(deps.as_ref(), "eth"));
+        assert!(query::chain_info(deps.as_ref(), "eth".to_string()).is_err());
+        register(deps.as_mut(), "creator", "eth", "eip155:1").unwrap();
+        assert!(!found(deps.as_ref(), "eth"));
+    }
+
+    #[test]
+    fn ownership_transfer_and_pause() {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let run = |deps: DepsMut, sender: &str, msg: ExecuteMsg| {
+            execute(deps, mock_env(), mock_info(sender, &[]), msg)
+        };
+        let ownership = |deps: Deps| -> Ownership {
+            from_json(query(deps, mock_env(), QueryMsg::Ownership {}).unwrap()).unwrap()
+        };
+        let pause = |paused: bool| ExecuteMsg::SetPaused { paused };
+
+        let err = run(deps.as_mut(), "owner", ExecuteMsg::AcceptOwnership {}).unwrap_err();
+        assert_eq!(err, ContractError::NoPendingOwner {});
+        let transfer = ExecuteMsg::TransferOwnership {
+            new_owner: "owner".to_string(),
+        };
+        let err = run(deps.as_mut(), "owner", transfer.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        run(deps.as_mut(), "creator", transfer).unwrap();
+        assert_eq!(
+            ownership(deps.as_ref()),
+            Ownership {
+                owner: Some(Addr::unchecked("creator")),
+                pending_owner: Some(Addr::unchecked("owner")),
+            }
+        );
+
+        // the current owner stays in charge until the transfer is accepted
+        let err = run(deps.as_mut(), "mallory", ExecuteMsg::AcceptOwnership {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        run(deps.as_mut(), "creator", pause(true)).unwrap();
+        run(deps.as_mut(), "owner", ExecuteMsg::AcceptOwnership {}).unwrap();
+        assert_eq!(
+            ownership(deps.as_ref()),
+            Ownership {
+                owner: Some(Addr::unchecked("owner")),
+                pending_owner: None,
+            }
+        );
+        let err = run(deps.as_mut(), "creator", pause(false)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // no roots are accepted while paused
+        set_submitters(deps.as_mut(), "foo", &["creator"], 1).unwrap_err();
+        run(
+            deps.as_mut(),
+            "owner",
+            ExecuteMsg::SetChainSubmitters {
+                chain_id: "foo".to_string(),
+                submitters: vec!["owner".to_string()],
+                threshold: 1,
+            },
+        )
+        .unwrap();
+        let submit = ExecuteMsg::SubmitRoot {
+            chain_id: "foo".to_string(),
+            root: Binary::from("root".as_bytes()),
+            height: 1,
+        };
+        let err = run(deps.as_mut(), "owner", submit.clone()).unwrap_err();
+        assert_eq!(err, ContractError::ContractPaused {});
+        let msg = SudoMsg {
+            data: vec![generic_ve(&[("foo", source_root("root"))], &[], 10, None)],
+            total_power: None,
+        };
+        let res = sudo(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(res.attributes, vec![Attribute::new("paused", "true")]);
+        run(deps.as_mut(), "owner", pause(false)).unwrap();
+        run(deps.as_mut(), "owner", submit).unwrap();
+
+        run(deps.as_mut(), "owner", ExecuteMsg::RenounceOwnership {}).unwrap();
+        assert_eq!(ownership(deps.as_ref()).owner, None);
+        let err = run(deps.as_mut(), "owner", pause(true)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn quorum_relative_to_bonded_power() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let msg = ExecuteMsg::UpdateConfig(ConfigUpdate {
+            default_strategy: Some(Strategy::WeightedThreshold),
+            ..Default::default()
+        });
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let msg = |total_power: Option<u64>| SudoMsg {
+            data: vec![generic_ve(&[("foo", source_root("root"))], &[], 100, None)],
+            total_power,
+        };
+
+        // a single validator reporting the chain is 100% of the participating power,
+        // but only 10% of the bonded power
+        sudo(deps.as_mut(), env.clone(), msg(Some(1000))).unwrap();
+        let root = Binary::from("root".as_bytes());
+        assert!(
+            query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), root.clone()).is_err()
+        );
+
+        // without a reported total only the received vote extensions are known
+        sudo(deps.as_mut(), env, msg(None)).unwrap();
+        assert!(query::lookup_hash(deps.as_ref(), mock_env(), "foo".to_string(), root).is_ok());
+    }
+
+    #[test]
+    fn equivocation_is_slashed_and_jailed() {
+        use crate::msg::SignedVote;
+        use crate::state::UNBONDING_QUEUE;
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        use k256::ecdsa::{Signature, SigningKey};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let info = mock_info("operator", &coins(1000, "stake"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        let info = mock_info("operator", &[]);
+        let msg = ExecuteMsg::Unbond {
+            amount: coins(200, "stake"),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let key = SigningKey::from_bytes(&[1u8; 32].into()).unwrap();
+        let other_key = SigningKey::from_bytes(&[2u8; 32].into()).unwrap();
+        let signed_vote = |key: &SigningKey, root: &str| {
+            let mut vote = SignedVote {
+                chain_id: "foo".to_string(),
+                root: source_root(root),
+                signature: Binary::default(),
+            };
+            let signature: Signature = key.sign_prehash(&vote.sign_bytes().unwrap()).unwrap();
+            vote.signature = Binary::from(signature.to_bytes().as_slice());
+            vote
+        };
+        let evidence = |vote_a: SignedVote, vote_b: SignedVote| ExecuteMsg::SubmitEvidence {
+            operator: "operator".to_string(),
+            vote_a,
+            vote_b,
+        };
+        let reporter = mock_info("reporter", &[]);
+
+        let msg = evidence(signed_vote(&key, "a"), signed_vote(&key, "b"));
+        let err = execute(deps.as_mut(), env.clone(), reporter.clone(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::OperatorKeyNotRegistered {
+                operator: "operator".to_string()
+            }
+        );
+
+        let msg = ExecuteMsg::RegisterOperator {
+            pubkey: Binary::from(key.verifying_key().to_encoded_point(true).as_bytes()),
+            key_type: KeyType::Secp256k1,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg).unwrap();
+
+        // votes for the same root, another height or signed by someone else prove nothing
+        let mut other_height = signed_vote(&key, "b");
+        other_height.root.height = 2;
+        for msg in [
+            evidence(signed_vote(&key, "a"), signed_vote(&key, "a")),
+            evidence(signed_vote(&key, "a"), other_height),
+            evidence(signed_vote(&key, "a"), signed_vote(&other_key, "b")),
+        ] {
+            let res = execute(deps.as_mut(), env.clone(), reporter.clone(), msg);
+            assert!(matches!(res, Err(ContractError::InvalidEvidence { .. })));
+        }
+
+        // 5% of the 800 bonded and 200 unbonding stake is slashed, 10% of which goes
+        // to the reporter
+        let msg = evidence(signed_vote(&key, "a"), signed_vote(&key, "b"));
+        let res = execute(deps.as_mut(), env.clone(), reporter.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                BankMsg::Send {
+                    to_address: "reporter".to_string(),
+                    amount: coins(5, "stake"),
+                }
+                .into(),
+                BankMsg::Burn {
+                    amount: coins(45, "stake"),
+                }
+                .into(),
+            ]
+        );
+        let operator = Addr::unchecked("operator");
+        assert_eq!(
+            STAKE_MAP.load(&deps.storage, operator.clone()).unwrap(),
+            coins(760, "stake")
+        );
+        assert_eq!(
+            TOTAL_STAKE.load(&deps.storage).unwrap(),
+            coins(760, "stake")
+        );
+        assert_eq!(
+            UNBONDING_QUEUE.load(&deps.storage, operator).unwrap()[0].amount,
+            coins(190, "stake")
+        );
+        let err = execute(deps.as_mut(), env.clone(), reporter, msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::EvidenceAlreadySubmitted { .. }
+        ));
+
+        // a jailed operator votes with zero power until its term is served
+        let msg = SudoMsg {
+            data: vec![generic_ve(
+                &[("foo", source_root("root"))],
+                &[],
+                1,
+                Some("operator"),
+            )],
+            total_power: None,
+        };
+        let root = Binary::from("root".as_bytes());
+        sudo(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+        assert!(
+            query::lookup_hash(deps.as_ref(), env.clone(), "foo".to_string(), root.clone())
+                .is_err()
+        );
+
+        env.block.time = env.block.time.plus_seconds(24 * 60 * 60);
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(query::lookup_hash(deps.as_ref(), env, "foo".to_string(), root).is_ok());
+    }
+
+    #[test]
+    fn operator_key_rotation() {
+        use crate::msg::{OperatorResponse, SignedVote};
+        use crate::state::{OperatorKey, PendingKey};
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        use k256::ecdsa::Signature;
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        let info = mock_info("operator", &coins(100, "stake"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+
+        let ed25519_key = ed25519_zebra::SigningKey::from([1u8; 32]);
+        let ed25519_pubkey = OperatorKey {
+            key_type: KeyType::Ed25519,
+            pubkey: Binary::from(
+                <[u8; 32]>::from(ed25519_zebra::VerificationKey::from(&ed25519_key)).as_slice(),
+            ),
+        };
+        let secp256k1_key = k256::ecdsa::SigningKey::from_bytes(&[2u8; 32].into()).unwrap();
+        let secp256k1_pubkey = OperatorKey {
+            key_type: KeyType::Secp256k1,
+            pubkey: Binary::from(
+                secp256k1_key
+                    .verifying_key()
+                    .to_encoded_point(false)
+                    .as_bytes(),
+            ),
+        };
+        let register = |key: &OperatorKey| ExecuteMsg::RegisterOperator {
+            pubkey: key.pubkey.clone(),
+            key_type: key.key_type.clone(),
+        };
+        let operator = |deps: Deps, env: Env| -> OperatorResponse {
+            let msg = QueryMsg::Operator {
+                address: "operator".to_string(),
+            };
+            from_json(query(deps, env, msg).unwrap()).unwrap()
+        };
+
+        for (pubkey, key_type) in [
+            (ed25519_pubkey.pubkey.clone(), KeyType::Secp256k1),
+            (secp256k1_pubkey.pubkey.clone(), KeyType::Ed25519),
+        ] {
+            let msg = ExecuteMsg::RegisterOperator {
+                pubkey,
+                key_type: key_type.clone(),
+            };
+            let err = execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg);
+            assert_eq!(
+                err.unwrap_err(),
+                ContractError::InvalidPubkey {
+                    key_type: key_type.to_string()
+                }
+            );
+        }
+
+        // the first key is active right away
+        let info = mock_info("operator", &[]);
+        execute(deps.as_mut(), env.clone(), info, register(&ed25519_pubkey)).unwrap();
+        let res = operator(deps.as_ref(), env.clone());
+        assert_eq!(res.key, Some(ed25519_pubkey.clone()));
+        assert_eq!(res.pending_key, None);
+        assert_eq!(res.stake, coins(100, "stake"));
+
+        // later keys only after the rotation delay
+        let info = mock_info("operator", &[]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            register(&secp256k1_pubkey),
+        )
+        .unwrap();
+        let active_at = env.block.time.plus_seconds(24 * 60 * 60);
+        let res = operator(deps.as_ref(), env.clone());
+        assert_eq!(res.key, Some(ed25519_pubkey.clone()));
+        assert_eq!(
+            res.pending_key,
+            Some(PendingKey {
+                key: secp256k1_pubkey.clone(),
+                active_at,
+            })
+        );
+
+        let ed25519_vote = |root: &str, height: u64| {
+            let mut vote = SignedVote {
+                chain_id: "foo".to_string(),
+                root: SourceRoot {
+                    root: Binary::from(root.as_bytes()),
+                    height,
+                },
+                signature: Binary::default(),
+            };
+            let signature = ed25519_key.sign(&vote.sign_bytes().unwrap());
+            vote.signature = Binary::from(<[u8; 64]>::from(signature).as_slice());
+            vote
+        };
+        let secp256k1_vote = |root: &str, height: u64| {
+            let mut vote = ed25519_vote(root, height);
+            let signature: Signature = secp256k1_key
+                .sign_prehash(&vote.sign_bytes().unwrap())
+                .unwrap();
+            vote.signature = Binary::from(signature.to_bytes().as_slice());
+            vote
+        };
+        let evidence = |vote_a: SignedVote, vote_b: SignedVote| ExecuteMsg::SubmitEvidence {
+            operator: "operator".to_string(),
+            vote_a,
+            vote_b,
+        };
+
+        // while rotating, votes signed with either key are the operator's
+        let msg = evidence(ed25519_vote("a", 1), secp256k1_vote("b", 1));
+        execute(deps.as_mut(), env.clone(), mock_info("reporter", &[]), msg).unwrap();
+
+        env.block.time = active_at;
+        let res = operator(deps.as_ref(), env.clone());
+        assert_eq!(res.key, Some(secp256k1_pubkey));
+        assert_eq!(res.pending_key, None);
+        assert_eq!(res.stake, coins(95, "stake"));
+        assert!(res.jailed_until.is_none());
+
+        let msg = evidence(ed25519_vote("a", 2), secp256k1_vote("b", 2));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("reporter", &[]), msg);
+        assert!(matches!(res, Err(ContractError::InvalidEvidence { .. })));
+        let msg = evidence(secp256k1_vote("a", 2), secp256k1_vote("b", 2));
+        execute(deps.as_mut(), env, mock_info("reporter", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn delegated_restaking() {
+        use crate::msg::{DelegationsResponse, OperatorResponse, SignedVote};
+        use crate::state::{Delegation, DELEGATED, DELEGATIONS, DELEGATOR_OPERATORS, UNDELEGATED};
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        use k256::ecdsa::{Signature, SigningKey};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let key = SigningKey::from_bytes(&[1u8; 32].into()).unwrap();
+        let msg = ExecuteMsg::RegisterOperator {
+            pubkey: Binary::from(key.verifying_key().to_encoded_point(true).as_bytes()),
+            key_type: KeyType::Secp256k1,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg).unwrap();
+        let info = mock_info("operator", &coins(100, "stake"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+
+        let delegate = |operator: &str| ExecuteMsg::Delegate {
+            operator: operator.to_string(),
+        };
+        let info = mock_info("alice", &coins(300, "stake"));
+        let err = execute(deps.as_mut(), env.clone(), info, delegate("nobody")).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::OperatorKeyNotRegistered {
+                operator: "nobody".to_string()
+            }
+        );
+        let info = mock_info("alice", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, delegate("operator")).unwrap_err();
+        assert_eq!(err, ContractError::EmptyDelegation {});
+        // the self-stake of the operator already holds its own reward position
+        let info = mock_info("operator", &coins(100, "stake"));
+        let err = execute(deps.as_mut(), env.clone(), info, delegate("operator")).unwrap_err();
+        assert_eq!(err, ContractError::SelfDelegation {});
+        let info = mock_info("alice", &coins(300, "stake"));
+        execute(deps.as_mut(), env.clone(), info, delegate("operator")).unwrap();
+        let info = mock_info("bob", &coins(100, "stake"));
+        execute(deps.as_mut(), env.clone(), info, delegate("operator")).unwrap();
+
+        let msg = ExecuteMsg::SetCommission {
+            rate: Decimal::percent(150),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg);
+        assert_eq!(err.unwrap_err(), ContractError::InvalidCommission {});
+        let msg = ExecuteMsg::SetCommission {
+            rate: Decimal::percent(10),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            msg.clone(),
+        );
+        assert!(err.is_err());
+        execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg).unwrap();
+
+        let res: OperatorResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Operator {
+                    address: "operator".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.stake, coins(100, "stake"));
+        assert_eq!(res.delegated, coins(400, "stake"));
+        assert_eq!(res.commission, Decimal::percent(10));
+
+        let delegation = |delegator: &str, amount: u128| Delegation {
+            delegator: Addr::unchecked(delegator),
+            operator: Addr::unchecked("operator"),
+            amount: coins(amount, "stake"),
+        };
+        let operator_delegations = |deps: Deps, start_after: Option<&str>, limit: u32| {
+            let msg = QueryMsg::OperatorDelegations {
+                operator: "operator".to_string(),
+                start_after: start_after.map(str::to_string),
+                limit: Some(limit),
+            };
+            from_json::<DelegationsResponse>(query(deps, mock_env(), msg).unwrap())
+                .unwrap()
+                .delegations
+        };
+        let delegator_delegations = |deps: Deps, delegator: &str| {
+            let msg = QueryMsg::DelegatorDelegations {
+                delegator: delegator.to_string(),
+                start_after: None,
+                limit: None,
+            };
+            from_json::<DelegationsResponse>(query(deps, mock_env(), msg).unwrap())
+                .unwrap()
+                .delegations
+        };
+        assert_eq!(
+            operator_delegations(deps.as_ref(), None, 10),
+            vec![delegation("alice", 300), delegation("bob", 100)]
+        );
+        assert_eq!(
+            operator_delegations(deps.as_ref(), Some("alice"), 1),
+            vec![delegation("bob", 100)]
+        );
+        assert_eq!(
+            delegator_delegations(deps.as_ref(), "alice"),
+            vec![delegation("alice", 300)]
+        );
+
+        // the operator votes with its own and its delegated stake
+        let msg = SudoMsg {
+            data: vec![generic_ve(
+                &[("foo", source_root("root"))],
+                &[],
+                1,
+                Some("operator"),
+            )],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        let res = query::lookup_root(
+            deps.as_ref(),
+            env.clone(),
+            "foo".to_string(),
+            Binary::from("root".as_bytes()),
+        )
+        .unwrap();
+        assert_eq!(res.power, Uint128::new(500));
+
+        let undelegate = |amount: u128| ExecuteMsg::Undelegate {
+            operator: "operator".to_string(),
+            amount: coins(amount, "stake"),
+        };
+        let info = mock_info("alice", &[]);
+        execute(deps.as_mut(), env.clone(), info, undelegate(100)).unwrap();
+        let info = mock_info("alice", &[]);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::ClaimUnbonded {},
+        );
+        assert_eq!(err.unwrap_err(), ContractError::NothingToClaim {});
+
+        // delegations and undelegations are slashed along with the operator
+        let signed_vote = |root: &str| {
+            let mut vote = SignedVote {
+                chain_id: "foo".to_string(),
+                root: source_root(root),
+                signature: Binary::default(),
+            };
+            let signature: Signature = key.sign_prehash(&vote.sign_bytes().unwrap()).unwrap();
+            vote.signature = Binary::from(signature.to_bytes().as_slice());
+            vote
+        };
+        let msg = ExecuteMsg::SubmitEvidence {
+            operator: "operator".to_string(),
+            vote_a: signed_vote("a"),
+            vote_b: signed_vote("b"),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("reporter", &[]), msg).unwrap();
+        assert_eq!(
+            operator_delegations(deps.as_ref(), None, 10),
+            vec![delegation("alice", 190), delegation("bob", 95)]
+        );
+        let operator = Addr::unchecked("operator");
+        let alice = Addr::unchecked("alice");
+        // the delegations themselves are only scaled down once they are touched
+        assert_eq!(
+            DELEGATIONS
+                .load(&deps.storage, (operator.clone(), alice.clone()))
+                .unwrap(),
+            coins(200, "stake")
+        );
+        assert_eq!(
+            UNDELEGATED.load(&deps.storage, operator.clone()).unwrap(),
+            coins(95, "stake")
+        );
+        assert_eq!(
+            DELEGATED.load(&deps.storage, operator.clone()).unwrap(),
+            coins(285, "stake")
+        );
+        assert_eq!(
+            TOTAL_STAKE.load(&deps.storage).unwrap(),
+            coins(380, "stake")
+        );
+        let pending: PendingUnbondingsResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::PendingUnbondings {
+                    address: "alice".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pending.undelegations.len(), 1);
+        assert_eq!(pending.undelegations[0].operator, operator);
+        assert_eq!(
+            pending.undelegations[0].unbondings[0].amount,
+            coins(95, "stake")
+        );
+
+        // fully undelegated coins stay indexed until they are claimed
+        let info = mock_info("alice", &[]);
+        execute(deps.as_mut(), env.clone(), info, undelegate(190)).unwrap();
+        assert_eq!(delegator_delegations(deps.as_ref(), "alice"), vec![]);
+        env.block.time = env.block.time.plus_seconds(60);
+        let info = mock_info("alice", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimUnbonded {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(285, "stake"),
+            }
+            .into()
+        );
+        assert!(!DELEGATOR_OPERATORS.has(&deps.storage, (alice.clone(), operator.clone())));
+        assert!(!DELEGATIONS.has(&deps.storage, (operator.clone(), alice)));
+        assert!(!UNDELEGATED.has(&deps.storage, operator));
+    }
+
+    #[test]
+    fn rewards_follow_finalized_votes() {
+        use crate::msg::PendingRewardsResponse;
+        use crate::state::{RewardsConfig, REWARD_POSITIONS};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let msg = ExecuteMsg::RegisterOperator {
+            pubkey: Binary::from([0x02; 33].as_slice()),
+            key_type: KeyType::Secp256k1,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("op_a", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetCommission {
+            rate: Decimal::percent(10),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("op_a", &[]), msg).unwrap();
+        for operator in ["op_a", "op_b"] {
+            let info = mock_info(operator, &coins(100, "stake"));
+            execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        }
+        let delegate = ExecuteMsg::Delegate {
+            operator: "op_a".to_string(),
+        };
+        let info = mock_info("alice", &coins(300, "stake"));
+        execute(deps.as_mut(), env.clone(), info, delegate.clone()).unwrap();
+
+        let info = mock_info("funder", &coins(1500, "reward"));
+        let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::FundRewards {});
+        assert_eq!(err.unwrap_err(), ContractError::RewardsDisabled {});
+        let update_rewards = |denom: &str| {
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                rewards: Some(RewardsConfig {
+                    denom: denom.to_string(),
+                    per_block: Uint128::new(1000),
+                }),
+                ..Default::default()
+            })
+        };
+        let info = mock_info("creator", &[]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            update_rewards("reward"),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info, update_rewards("other"));
+        assert_eq!(err.unwrap_err(), ContractError::RewardDenomChanged {});
+        let info = mock_info("funder", &coins(1500, "other"));
+        let err = execute(deps.as_mut(), env.clone(), info, ExecuteMsg::FundRewards {});
+        assert_eq!(
+            err.unwrap_err(),
+            ContractError::InvalidRewardFunds {
+                denom: "reward".to_string()
+            }
+        );
+        let info = mock_info("funder", &coins(1500, "reward"));
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::FundRewards {}).unwrap();
+
+        // "op_a" votes for the finalized root with 400 power, "op_b" is outvoted
+        let msg = SudoMsg {
+            data: [("op_a", "root"), ("op_b", "other")]
+                .into_iter()
+                .map(|(operator, root)| {
+                    generic_ve(&[("foo", source_root(root))], &[], 1, Some(operator))
+                })
+                .collect(),
+            total_power: None,
+        };
+        let res = sudo(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("rewards_distributed", "1000")));
+
+        let pending_rewards = |deps: Deps, address: &str| -> PendingRewardsResponse {
+            let msg = QueryMsg::PendingRewards {
+                address: address.to_string(),
+            };
+            from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+        // 10% commission, the rest split 100:300 between the operator and its delegator
+        assert_eq!(
+            pending_rewards(deps.as_ref(), "op_a").rewards,
+            coins(325, "reward")
+        );
+        assert_eq!(
+            pending_rewards(deps.as_ref(), "alice").rewards,
+            coins(675, "reward")
+        );
+        assert_eq!(
+            pending_rewards(deps.as_ref(), "op_b"),
+            PendingRewardsResponse {
+                rewards: vec![],
+                pool: coins(500, "reward"),
+            }
+        );
+
+        // the pool only has 500 left, split 100:400 after alice delegates more
+        let info = mock_info("alice", &coins(100, "stake"));
+        execute(deps.as_mut(), env.clone(), info, delegate).unwrap();
+        sudo(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            pending_rewards(deps.as_ref(), "op_a").rewards,
+            coins(465, "reward")
+        );
+
+        let info = mock_info("alice", &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::ClaimRewards {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: coins(1035, "reward"),
+            }
+            .into()
+        );
+        assert_eq!(pending_rewards(deps.as_ref(), "alice").rewards, vec![]);
+        let info = mock_info("op_b", &[]);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::ClaimRewards {},
+        );
+        assert_eq!(err.unwrap_err(), ContractError::NoRewards {});
+
+        // nothing is left to distribute
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(!res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "rewards_distributed"));
+
+        // changing the weights leaves the shares to be recomputed in batches
+        let msg = ExecuteMsg::UpdateConfig(ConfigUpdate {
+            denom_weights: Some(BTreeMap::from([(
+                "stake".to_string(),
+                Decimal::percent(50),
+            )])),
+            ..Default::default()
+        });
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        let shares = |deps: Deps, staker: &str| {
+            REWARD_POSITIONS
+                .load(
+                    deps.storage,
+                    (Addr::unchecked(staker), Addr::unchecked("op_a")),
+                )
+                .unwrap()
+                .shares
+        };
+        assert_eq!(shares(deps.as_ref(), "alice"), Uint128::new(400));
+        let reweight = ExecuteMsg::Reweight { limit: Some(2) };
+        let info = mock_info("anyone", &[]);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), reweight.clone()).unwrap();
+        assert!(res.attributes.contains(&Attribute::new("done", "false")));
+        assert_eq!(shares(deps.as_ref(), "op_a"), Uint128::new(50));
+        assert_eq!(shares(deps.as_ref(), "alice"), Uint128::new(400));
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), reweight.clone()).unwrap();
+        assert!(res.attributes.contains(&Attribute::new("done", "true")));
+        assert_eq!(shares(deps.as_ref(), "alice"), Uint128::new(200));
+        let err = execute(deps.as_mut(), env, info, reweight);
+        assert_eq!(err.unwrap_err(), ContractError::NothingToReweight {});
+    }
+
+    #[test]
+    fn downtime_is_jailed() {
+        use crate::msg::OperatorResponse;
+        use crate::state::{LivenessConfig, SigningInfo};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let mut msg = default_instantiate_msg();
+        msg.power_source = PowerSource::Stake;
+        msg.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        register_chain(deps.as_mut(), "foo");
+        let register = |mut deps: DepsMut, operator: &str, stake: u128| {
+            let info = mock_info(operator, &coins(stake, "stake"));
+            execute(deps.branch(), mock_env(), info, ExecuteMsg::Stake {}).unwrap();
+            let msg = ExecuteMsg::RegisterOperator {
+                pubkey: Binary::from([0x02; 33].as_slice()),
+                key_type: KeyType::Secp256k1,
+            };
+            execute(deps, mock_env(), mock_info(operator, &[]), msg)
+        };
+        // "idle" registers but never votes
+        for (operator, stake) in [("op_a", 500), ("op_b", 100), ("idle", 100)] {
+            register(deps.as_mut(), operator, stake).unwrap();
+        }
+
+        let liveness = LivenessConfig {
+            window: 4,
+            min_signed: Decimal::percent(50),
+            slash_fraction: Decimal::percent(1),
+            jail_duration: 60,
+        };
+        let update_liveness = |config: LivenessConfig| {
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                liveness: Some(config),
+                ..Default::default()
+            })
+        };
+        let info = mock_info("creator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            update_liveness(LivenessConfig {
+                window: 0,
+                ..liveness.clone()
+            }),
+        );
+        assert_eq!(err.unwrap_err(), ContractError::InvalidLiveness {});
+        execute(deps.as_mut(), env.clone(), info, update_liveness(liveness)).unwrap();
+
+        let votes = |votes: &[(&str, &str)]| SudoMsg {
+            data: votes
+                .iter()
+                .map(|(operator, root)| {
+                    generic_ve(&[("foo", source_root(root))], &[], 1, Some(operator))
+                })
+                .collect(),
+            total_power: None,
+        };
+        let signing_info = |deps: Deps, operator: &str| -> SigningInfo {
+            let msg = QueryMsg::SigningInfo {
+                operator: operator.to_string(),
+            };
+            from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+
+        // "op_b" is outvoted in the first block and misses the next three
+        let first_height = env.block.height;
+        sudo(
+            deps.as_mut(),
+            env.clone(),
+            votes(&[("op_a", "root0"), ("op_b", "other")]),
+        )
+        .unwrap();
+        for i in 1..3 {
+            env.block.height += 1;
+            let root = format!("root{i}");
+            let res = sudo(deps.as_mut(), env.clone(), votes(&[("op_a", &root)])).unwrap();
+            assert!(res.events.iter().all(|e| e.ty != "operator_jailed"));
+        }
+        let info = signing_info(deps.as_ref(), "op_b");
+        assert_eq!(info.start_height, first_height);
+        assert_eq!(info.index_offset, 3);
+        assert_eq!(info.missed_blocks, 2);
+        assert_eq!(info.missed_roots, 2);
+        assert_eq!(info.minority_votes, 1);
+        assert_eq!(info.last_voted_height, Some(first_height));
+
+        // the window is full and "op_b" signed less than half of it
+        env.block.height += 1;
+        env.block.time = env.block.time.plus_seconds(5);
+        let res = sudo(deps.as_mut(), env.clone(), votes(&[("op_a", "root3")])).unwrap();
+        let jailed_until = env.block.time.plus_seconds(60);
+        assert_eq!(
+            res.events
+                .into_iter()
+                .filter(|event| event.ty == "operator_jailed")
+                .collect::<Vec<_>>(),
+            vec![Event::new("operator_jailed")
+                .add_attribute("operator", "op_b")
+                .add_attribute("reason", "downtime")
+                .add_attribute("jailed_until", jailed_until.to_string())]
+        );
+        assert_eq!(
+            res.messages[0].msg,
+            BankMsg::Burn {
+                amount: coins(1, "stake")
+            }
+            .into()
+        );
+        let msg = QueryMsg::Operator {
+            address: "op_b".to_string(),
+        };
+        let operator: OperatorResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(operator.stake, coins(99, "stake"));
+        assert_eq!(operator.jailed_until, Some(jailed_until));
+
+        let info = signing_info(deps.as_ref(), "op_b");
+        assert_eq!(info.index_offset, 0);
+        assert_eq!(info.missed_blocks, 0);
+        assert_eq!(info.missed_roots, 3);
+        let info = signing_info(deps.as_ref(), "op_a");
+        assert_eq!(info.missed_blocks, 0);
+        assert_eq!(info.missed_roots, 0);
+        assert_eq!(info.last_voted_height, Some(env.block.height));
+
+        // operators that never voted are not tracked
+        assert_eq!(signing_info(deps.as_ref(), "idle"), SigningInfo::default());
+        assert!(!JAILED.has(deps.as_ref().storage, Addr::unchecked("idle")));
+    }
+
+    #[test]
+    fn jailing_drops_pending_votes() {
+        let mut deps = mock_dependencies();
+        let ve = |operator: &str| VoteExtension {
+            vote: Vote {
+                roots: BTreeMap::from([
+                    ("bar".to_string(), source_root("root")),
+                    ("foo".to_string(), source_root("root")),
+                ]),
+                prices: BTreeMap::new(),
+            },
+            ve_power: Uint128::one(),
+            operator: Some(operator.to_string()),
+        };
+        let votes = vec![ve("op_a"), ve("op_b")];
+        for chain_id in ["bar", "foo"] {
+            save_pending_votes(deps.as_mut().storage, 1, chain_id, &votes).unwrap();
+        }
+
+        execute::jail(
+            deps.as_mut().storage,
+            &Addr::unchecked("op_a"),
+            mock_env().block.time,
+        )
+        .unwrap();
+        let pending: Vec<(String, String)> = PENDING_VOTES
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(
+            pending,
+            vec![
+                ("bar".to_string(), "op_b".to_string()),
+                ("foo".to_string(), "op_b".to_string())
+            ]
+        );
+        assert!(OPERATOR_PENDING_VOTES
+            .prefix("op_a".to_string())
+            .is_empty(deps.as_ref().storage));
+    }
+
+    #[test]
+    fn prices_are_stake_weighted_medians() {
+        use crate::msg::{PriceResponse, PricesResponse};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+
+        let vote = |prices: &[(&str, u128)], ve_power: u64| generic_ve(&[], prices, ve_power, None);
+        // "ETH/USD" is only reported by 40 of the 100 power and does not reach quorum
+        let msg = SudoMsg {
+            data: vec![
+                vote(&[("BTC/USD", 100), ("ETH/USD", 10)], 40),
+                vote(&[("BTC/USD", 300)], 30),
+                vote(&[("BTC/USD", 200)], 30),
+            ],
+            total_power: Some(100),
+        };
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("prices_updated", "1")));
+
+        let get_price = |deps: Deps, pair: &str| {
+            let msg = QueryMsg::GetPrice {
+                pair: pair.to_string(),
+            };
+            query(deps, mock_env(), msg).and_then(from_json::<PriceResponse>)
+        };
+        let btc = PriceResponse {
+            pair: "BTC/USD".to_string(),
+            price: Uint128::new(200),
+            timestamp: env.block.time,
+            height: env.block.height,
+            power: Uint128::new(100),
+        };
+        assert_eq!(get_price(deps.as_ref(), "BTC/USD").unwrap(), btc);
+        assert!(get_price(deps.as_ref(), "ETH/USD").is_err());
+
+        // votes without prices keep the last price, a new quorum replaces it
+        env.block.height += 1;
+        let msg = SudoMsg {
+            data: vec![vote(&[], 100)],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(get_price(deps.as_ref(), "BTC/USD").unwrap(), btc);
+        env.block.height += 1;
+        let msg = SudoMsg {
+            data: vec![
+                vote(&[("BTC/USD", 250), ("ETH/USD", 20)], 50),
+                vote(&[("BTC/USD", 150), ("ETH/USD", 10)], 50),
+            ],
+            total_power: None,
+        };
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+
+        let msg = QueryMsg::GetPrices {
+            pairs: vec!["ETH/USD".to_string(), "BTC/USD".to_string()],
+        };
+        let res: PricesResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res.prices
+                .iter()
+                .map(|price| (price.pair.as_str(), price.price.u128(), price.height))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ETH/USD", 10, env.block.height),
+                ("BTC/USD", 150, env.block.height)
+            ]
+        );
+        let msg = QueryMsg::GetPrices {
+            pairs: vec!["BTC/USD".to_string(), "SOL/USD".to_string()],
+        };
+        assert!(query(deps.as_ref(), env, msg).is_err());
+    }
+
+    #[test]
+    fn votes_without_prices_decode() {
+        let roots = BTreeMap::from([("foo".to_string(), source_root("root"))]);
+        let vote = Vote {
+            roots: roots.clone(),
+            prices: BTreeMap::from([("BTC/USD".to_string(), Uint128::new(100))]),
+        };
+        assert_eq!(Vote::decode(&serialize(&vote).unwrap()).unwrap(), vote);
+        // the encoding of votes from before prices were added
+        let legacy = serialize(&(roots.clone(),)).unwrap();
+        assert_eq!(
+            Vote::decode(&legacy).unwrap(),
+            Vote {
+                roots,
+                prices: BTreeMap::new(),
+            }
+        );
+        assert!(Vote::decode(&legacy[..legacy.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn votes_with_binary_roots_decode() {
+        // the encoding of votes from before roots carried their source height
+        let roots = BTreeMap::from([
+            ("bar".to_string(), Binary::from("bar".as_bytes())),
+            ("foo".to_string(), Binary::from("foo".as_bytes())),
+        ]);
+        let legacy = serialize(&(roots,)).unwrap();
+        let unknown_height = |root: &str| SourceRoot {
+            root: Binary::from(root.as_bytes()),
+            height: 0,
+        };
+        assert_eq!(
+            Vote::decode(&legacy).unwrap(),
+            Vote {
+                roots: BTreeMap::from([
+                    ("bar".to_string(), unknown_height("bar")),
+                    ("foo".to_string(), unknown_height("foo")),
+                ]),
+                prices: BTreeMap::new(),
+            }
+        );
+        assert!(Vote::decode(&legacy[..legacy.len() - 1]).is_err());
+        let mut trailing = legacy;
+        trailing.push(0);
+        assert!(Vote::decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn finalization_events() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "bar");
+        let msg = ExecuteMsg::RegisterChain {
+            chain_id: "foo".to_string(),
+            family: ChainFamily::Cosmos,
+            caip2: "cosmos:foo".to_string(),
+            hash_function: HashFunction::Sha256,
+            root_kind: RootKind::AppHash,
+            retention: Some(Retention {
+                max_roots: 1,
+                max_age_seconds: None,
+                max_age_blocks: None,
+            }),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let vote = |roots: &[(&str, &str)], ve_power: u64| {
+            let roots: Vec<_> = roots
+                .iter()
+                .map(|(chain_id, root)| (*chain_id, source_root(root)))
+                .collect();
+            generic_ve(&roots, &[], ve_power, None)
+        };
+        let root_hex = |root: &str| hex::encode(root.as_bytes());
+
+        // half of the validator set votes, "bar" is split 60:40
+        let msg = SudoMsg {
+            data: vec![
+                vote(&[("foo", "root1"), ("bar", "x")], 60),
+                vote(&[("foo", "root1"), ("bar", "y")], 40),
+            ],
+            total_power: Some(200),
+        };
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(
+            res.events,
+            vec![
+                Event::new("root_finalized")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", root_hex("root1"))
+                    .add_attribute("source_height", "1")
+                    .add_attribute("seq", "0")
+                    .add_attribute("power", "100")
+                    .add_attribute("participating_power", "100")
+                    .add_attribute("total_power", "200")
+                    .add_attribute("participation", "0.5"),
+                Event::new("quorum_not_reached")
+                    .add_attribute("chain_id", "bar")
+                    .add_attribute("leading_root", root_hex("x"))
+                    .add_attribute("leading_power", "60")
+                    .add_attribute("participating_power", "100")
+                    .add_attribute("total_power", "200")
+                    .add_attribute("participation", "0.5"),
+            ]
+        );
+
+        // "foo" only keeps its latest root
+        let msg = SudoMsg {
+            data: vec![vote(&[("foo", "root2")], 100)],
+            total_power: None,
+        };
+        let res = sudo(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.events,
+            vec![
+                Event::new("root_finalized")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", root_hex("root2"))
+                    .add_attribute("source_height", "1")
+                    .add_attribute("seq", "1")
+                    .add_attribute("power", "100")
+                    .add_attribute("participating_power", "100")
+                    .add_attribute("total_power", "100")
+                    .add_attribute("participation", "1"),
+                Event::new("root_evicted")
+                    .add_attribute("chain_id", "foo")
+                    .add_attribute("root", root_hex("root1"))
+                    .add_attribute("seq", "0"),
+            ]
+        );
+
+        let res = sudo(deps.as_mut(), env, msg).unwrap();
+        assert_eq!(
+            res.events,
+            vec![Event::new("duplicate_root")
+                .add_attribute("chain_id", "foo")
+                .add_attribute("root", root_hex("root2"))
+                .add_attribute("seq", "1")]
+        );
+    }
+
+    #[test]
+    fn paginated_root_and_stake_queries() {
+        use crate::msg::{
+            ListRootsResponse, ListStakersResponse, RootResponse, StakeResponse, TotalStakeResponse,
+        };
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::RegisterChain {
+            chain_id: "foo".to_string(),
+            family: ChainFamily::Cosmos,
+            caip2: "cosmos:foo".to_string(),
+            hash_function: HashFunction::Sha256,
+            root_kind: RootKind::AppHash,
+            retention: Some(Retention {
+                max_roots: 2,
+                max_age_seconds: None,
+                max_age_blocks: Some(5),
+            }),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        set_submitters(deps.as_mut(), "foo", &["creator"], 1).unwrap();
+        for height in 0..3 {
+            let msg = ExecuteMsg::SubmitRoot {
+                chain_id: "foo".to_string(),
+                root: Binary::from(format!("root{height}").as_bytes()),
+                height,
+            };
+            execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        }
+
+        let msg = QueryMsg::LatestRoot {
+            chain_id: "foo".to_string(),
+        };
+        let latest: RootResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(
+            (
+                latest.seq,
+                latest.root.source_height,
+                latest.age,
+                latest.expired
+            ),
+            (2, 2, 1, false)
+        );
+        let msg = QueryMsg::LatestRoot {
+            chain_id: "bar".to_string(),
+        };
+        assert!(query(deps.as_ref(), env.clone(), msg).is_err());
+
+        // the first root was evicted, the rest are listed oldest first
+        let list_roots = |env: Env, start_after: Option<u64>| -> Vec<(u64, bool)> {
+            let msg = QueryMsg::ListRoots {
+                chain_id: "foo".to_string(),
+                start_after,
+                limit: Some(1),
+            };
+            let res: ListRootsResponse =
+                from_json(query(deps.as_ref(), env, msg).unwrap()).unwrap();
+            res.roots
+                .into_iter()
+                .map(|root| (root.seq, root.expired))
+                .collect()
+        };
+        assert_eq!(list_roots(env.clone(), None), vec![(1, false)]);
+        assert_eq!(list_roots(env.clone(), Some(1)), vec![(2, false)]);
+        assert_eq!(list_roots(env.clone(), Some(2)), vec![]);
+        env.block.height += 6;
+        assert_eq!(list_roots(env.clone(), None), vec![(1, true)]);
+
+        for (staker, amount) in [("bob", 50), ("alice", 100)] {
+            let info = mock_info(staker, &coins(amount, "stake"));
+            execute(deps.as_mut(), env.clone(), info, ExecuteMsg::Stake {}).unwrap();
+        }
+        let list_stakers = |start_after: Option<&str>| -> ListStakersResponse {
+            let msg = QueryMsg::ListStakers {
+                start_after: start_after.map(str::to_string),
+                limit: Some(1),
+            };
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let alice = StakeResponse {
+            address: Addr::unchecked("alice"),
+            stake: coins(100, "stake"),
+        };
+        assert_eq!(list_stakers(None).stakers, vec![alice.clone()]);
+        assert_eq!(
+            list_stakers(Some("alice")).stakers,
+            vec![StakeResponse {
+                address: Addr::unchecked("bob"),
+                stake: coins(50, "stake"),
+            }]
+        );
+        assert_eq!(list_stakers(Some("bob")).stakers, vec![]);
+
+        let msg = QueryMsg::StakeOf {
+            address: "alice".to_string(),
+        };
+        let res: StakeResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res, alice);
+        let msg = QueryMsg::StakeOf {
+            address: "carol".to_string(),
+        };
+        let res: StakeResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.stake, vec![]);
+        let res: TotalStakeResponse =
+            from_json(query(deps.as_ref(), env, QueryMsg::TotalStake {}).unwrap()).unwrap();
+        assert_eq!(res.total_stake, coins(150, "stake"));
+    }
+
+    #[test]
+    fn simulate_aggregation_matches_sudo() {
+        use crate::msg::{ChainAggregationResponse, SimulateAggregationResponse};
+        use crate::state::RootTally;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+        register_chain(deps.as_mut(), "bar");
+
+        let vote = |roots: &[(&str, &str)], ve_power: u64| {
+            let roots: Vec<_> = roots
+                .iter()
+                .map(|(chain_id, root)| (*chain_id, source_root(root)))
+                .collect();
+            generic_ve(&roots, &[], ve_power, None)
+        };
+        let data = vec![
+            vote(&[("foo", "a"), ("bar", "x")], 60),
+            vote(&[("foo", "a"), ("bar", "y"), ("baz", "z")], 40),
+            GenericVE {
+                vote: Binary::from(&[0xff; 3][..]),
+                ve_power: 10,
+                operator: None,
+            },
+        ];
+        let msg = QueryMsg::SimulateAggregation {
+            data: data.clone(),
+            total_power: Some(150),
+        };
+        let res: SimulateAggregationResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            SimulateAggregationResponse {
+                chains: vec![
+                    ChainAggregationResponse {
+                        chain_id: "bar".to_string(),
+                        roots: vec![
+                            RootTally {
+                                root: source_root("x"),
+                                power: Uint128::new(60),
+                            },
+                            RootTally {
+                                root: source_root("y"),
+                                power: Uint128::new(40),
+                            },
+                        ],
+                        participating_power: Uint128::new(100),
+                        total_power: Uint128::new(150),
+                        winner: Some(source_root("x")),
+                        quorum_met: false,
+                    },
+                    ChainAggregationResponse {
+                        chain_id: "foo".to_string(),
+                        roots: vec![RootTally {
+                            root: source_root("a"),
+                            power: Uint128::new(100),
+                        }],
+                        participating_power: Uint128::new(100),
+                        total_power: Uint128::new(150),
+                        winner: Some(source_root("a")),
+                        quorum_met: true,
+                    },
+                ],
+                ignored_chains: vec!["baz".to_string()],
+                tally_overflow: vec![],
+                malformed_vote_extensions: 1,
+                duplicate_operators: vec![],
+            }
+        );
+        // nothing was stored
+        let lookup = |deps: Deps| {
+            query::lookup_root(deps, mock_env(), "foo".to_string(), source_root("a").root)
+        };
+        assert!(lookup(deps.as_ref()).is_err());
+
+        // sudo ignores every chain while paused
+        let pause = |deps: DepsMut, paused: bool| {
+            let msg = ExecuteMsg::SetPaused { paused };
+            execute(deps, mock_env(), mock_info("creator", &[]), msg).unwrap();
+        };
+        pause(deps.as_mut(), true);
+        let msg = QueryMsg::SimulateAggregation {
+            data: data.clone(),
+            total_power: Some(150),
+        };
+        let res: SimulateAggregationResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert!(res.chains.is_empty());
+        assert_eq!(res.ignored_chains, vec!["bar", "baz", "foo"]);
+        pause(deps.as_mut(), false);
+
+        let msg = SudoMsg {
+            data,
+            total_power: Some(150),
+        };
+        let res = sudo(deps.as_mut(), env, msg).unwrap();
+        assert_eq!(lookup(deps.as_ref()).unwrap().power, Uint128::new(100));
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("ignored_chain", "baz")));
+        assert_eq!(
+            res.events
+                .iter()
+                .map(|event| event.ty.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "root_finalized",
+                "quorum_not_reached",
+                "malformed_vote_extension"
+            ]
+        );
+    }
+
+    #[test]
+    fn root_attestations() {
+        use crate::state::{Attestation, RootTally, Voter};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            default_instantiate_msg(),
+        )
+        .unwrap();
+        register_chain(deps.as_mut(), "foo");
+
+        let vote = |operator: Option<&str>, root: &str, ve_power: u64| {
+            generic_ve(&[("foo", source_root(root))], &[], ve_power, operator)
+        };
+        let msg = |root: &str, other: &str| SudoMsg {
+            data: vec![
+                vote(Some("op_a"), root, 60),
+                vote(Some("op_b"), root, 30),
+                vote(Some("op_c"), other, 20),
+                vote(None, root, 10),
+            ],
+            total_power: Some(200),
+        };
+        let attestation = |deps: Deps, root: &str| {
+            let msg = QueryMsg::RootAttestation {
+                chain_id: "foo".to_string(),
+                root: source_root(root).root,
+            };
+            query(deps, mock_env(), msg).and_then(from_json::<Attestation>)
+        };
+
+        // attestations are not recorded by default
+        sudo(deps.as_mut(), env.clone(), msg("a", "b")).unwrap();
+        assert!(attestation(deps.as_ref(), "a").is_err());
+
+        let update = ExecuteMsg::UpdateConfig(ConfigUpdate {
+            record_attestations: Some(true),
+            ..Default::default()
+        });
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            update,
+        )
+        .unwrap();
+        sudo(deps.as_mut(), env.clone(), msg("c", "d")).unwrap();
+        assert_eq!(
+            attestation(deps.as_ref(), "c").unwrap(),
+            Attestation {
+                voters: vec![
+                    Voter {
+                        operator: "op_a".to_string(),
+                        power: Uint128::new(60),
+                    },
+                    Voter {
+                        operator: "op_b".to_string(),
+                        power: Uint128::new(30),
+                    },
+                ],
+                power_for: Uint128::new(100),
+                power_against: Uint128::new(20),
+                total_power: Uint128::new(200),
+                competing_roots: vec![RootTally {
+                    root: source_root("d"),
+                    power: Uint128::new(20),
+                }],
+            }
+        );
+
+        // the attestation goes along with its root
+        let msg = ExecuteMsg::DeregisterChain {
+            chain_id: "foo".to_string(),
+        };
+        execute(deps.as_mut(), env, mock_info("creator", &[]), msg).unwrap();
+        assert!(attestation(deps.as_ref(), "c").is_err());
     }
 }
@@ -13,6 +13,112 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Contract is paused")]
+    ContractPaused {},
+
+    #[error("No ownership transfer is pending")]
+    NoPendingOwner {},
+
+    #[error("Cannot migrate from contract {contract}")]
+    WrongContract { contract: String },
+
+    #[error("Cannot migrate from version {from} down to {to}")]
+    MigrationDowngrade { from: String, to: String },
+
+    #[error("Migrating from version {from} requires an initial config with an owner")]
+    MissingMigrationConfig { from: String },
+
+    #[error("Unbond amount must contain at least one non-zero coin")]
+    EmptyUnbond {},
+
+    #[error("Insufficient stake of {denom} to unbond")]
+    InsufficientStake { denom: String },
+
+    #[error("No unbonded stake is ready to be claimed")]
+    NothingToClaim {},
+
+    #[error("Threshold must be greater than zero and at most one")]
+    InvalidThreshold {},
+
+    #[error("Vote window must be at least one block")]
+    InvalidVoteWindow {},
+
+    #[error("Retention must keep at least one root and limit age to at least one second or block")]
+    InvalidRetention {},
+
+    #[error("Submitter threshold must be between one and the number of submitters")]
+    InvalidSubmitterThreshold {},
+
+    #[error("{submitter} already submitted this root")]
+    DuplicateSubmission { submitter: String },
+
+    #[error("Root at height {height} is older than the oldest root kept for {chain_id}")]
+    StaleRoot { chain_id: String, height: u64 },
+
+    #[error("Chain {chain_id} is not registered")]
+    ChainNotRegistered { chain_id: String },
+
+    #[error("Chain {chain_id} is already registered")]
+    ChainAlreadyRegistered { chain_id: String },
+
+    #[error("Chain {chain_id} is paused")]
+    ChainPaused { chain_id: String },
+
+    #[error("{caip2} is not a valid CAIP-2 chain id")]
+    InvalidCaip2 { caip2: String },
+
+    #[error("Slash fraction and reporter bounty must be at most one")]
+    InvalidSlashing {},
+
+    #[error("Rewards are not enabled")]
+    RewardsDisabled {},
+
+    #[error("Rewards are funded in {denom} only")]
+    InvalidRewardFunds { denom: String },
+
+    #[error("Reward denom cannot be changed once set")]
+    RewardDenomChanged {},
+
+    #[error("No rewards to claim")]
+    NoRewards {},
+
+    #[error("Reward shares are already up to date")]
+    NothingToReweight {},
+
+    #[error("Liveness window must be at least one block and fractions at most one")]
+    InvalidLiveness {},
+
+    #[error("Commission rate must be at most one")]
+    InvalidCommission {},
+
+    #[error("Delegation must contain at least one non-zero coin")]
+    EmptyDelegation {},
+
+    #[error("Operators cannot delegate to themselves, they stake instead")]
+    SelfDelegation {},
+
+    #[error("Invalid {key_type} public key")]
+    InvalidPubkey { key_type: String },
+
+    #[error("Operator {operator} has not registered a signing key")]
+    OperatorKeyNotRegistered { operator: String },
+
+    #[error("Invalid evidence: {reason}")]
+    InvalidEvidence { reason: String },
+
+    #[error("Evidence for operator {operator} on chain {chain_id} at height {height} was already submitted")]
+    EvidenceAlreadySubmitted {
+        operator: String,
+        chain_id: String,
+        height: u64,
+    },
+
+    #[error("Vote power of operator {operator} overflows")]
+    VotePowerOverflow { operator: String },
+
+    #[error("Vote power tally of chain {chain_id} overflows")]
+    TallyOverflow { chain_id: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
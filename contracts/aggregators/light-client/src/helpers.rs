@@ -0,0 +1,103 @@
+use cosmwasm_std::{Api, Coin, Decimal, OverflowError, Uint128};
+use std::collections::BTreeMap;
+
+use crate::state::{KeyType, OperatorKey};
+use crate::ContractError;
+
+/// Returns `coins` with `added` merged in, summing the amounts of equal denoms.
+pub fn add_coins(mut coins: Vec<Coin>, added: &[Coin]) -> Result<Vec<Coin>, OverflowError> {
+    for added_coin in added {
+        match coins.iter_mut().find(|coin| coin.denom == added_coin.denom) {
+            Some(coin) => coin.amount = coin.amount.checked_add(added_coin.amount)?,
+            None => coins.push(added_coin.clone()),
+        }
+    }
+    Ok(coins)
+}
+
+/// Returns `coins` with `removed` taken out, dropping denoms that reach zero.
+/// Fails with `InsufficientStake` if any denom of `removed` is not fully covered.
+pub fn sub_coins(mut coins: Vec<Coin>, removed: &[Coin]) -> Result<Vec<Coin>, ContractError> {
+    for removed_coin in removed {
+        let insufficient = || ContractError::InsufficientStake {
+            denom: removed_coin.denom.clone(),
+        };
+        let coin = coins
+            .iter_mut()
+            .find(|coin| coin.denom == removed_coin.denom)
+            .ok_or_else(insufficient)?;
+        coin.amount = coin
+            .amount
+            .checked_sub(removed_coin.amount)
+            .map_err(|_| insufficient())?;
+    }
+    coins.retain(|coin| !coin.amount.is_zero());
+    Ok(coins)
+}
+
+/// Returns the power of `coins` with every denom weighted by `denom_weights`, or
+/// `None` if it overflows. Denoms without a weight do not contribute any power.
+pub fn weighted_power(
+    coins: &[Coin],
+    denom_weights: &BTreeMap<String, Decimal>,
+) -> Option<Uint128> {
+    coins.iter().try_fold(Uint128::zero(), |power, coin| {
+        match denom_weights.get(&coin.denom) {
+            Some(weight) => power
+                .checked_add(coin.amount.checked_mul_floor(*weight).ok()?)
+                .ok(),
+            None => Some(power),
+        }
+    })
+}
+
+/// Splits `fraction` of every coin off `coins`, rounding down. Returns the remaining
+/// and the split off coins, neither of which contains zero amounts.
+pub fn split_coins(coins: Vec<Coin>, fraction: Decimal) -> (Vec<Coin>, Vec<Coin>) {
+    let mut split: Vec<Coin> = vec![];
+    let mut remaining: Vec<Coin> = vec![];
+    for mut coin in coins {
+        let split_amount = coin.amount.mul_floor(fraction);
+        coin.amount -= split_amount;
+        if !split_amount.is_zero() {
+            split.push(Coin::new(split_amount.u128(), coin.denom.clone()));
+        }
+        if !coin.amount.is_zero() {
+            remaining.push(coin);
+        }
+    }
+    (remaining, split)
+}
+
+/// Checks that the public key of `key` is well formed for its key type.
+pub fn validate_operator_key(key: &OperatorKey) -> Result<(), ContractError> {
+    let valid = match key.key_type {
+        KeyType::Secp256k1 => match key.pubkey.as_slice() {
+            [0x02 | 0x03, rest @ ..] => rest.len() == 32,
+            [0x04, rest @ ..] => rest.len() == 64,
+            _ => false,
+        },
+        KeyType::Ed25519 => key.pubkey.len() == 32,
+    };
+    if !valid {
+        return Err(ContractError::InvalidPubkey {
+            key_type: key.key_type.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `signature` is a signature of `message` by `key`. Malformed
+/// signatures do not verify.
+pub fn verify_signature(
+    api: &dyn Api,
+    key: &OperatorKey,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    match key.key_type {
+        KeyType::Secp256k1 => api.secp256k1_verify(message, signature, &key.pubkey),
+        KeyType::Ed25519 => api.ed25519_verify(message, signature, &key.pubkey),
+    }
+    .unwrap_or(false)
+}
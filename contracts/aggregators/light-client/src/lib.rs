@@ -1,6 +1,12 @@
+pub mod aggregation;
 pub mod contract;
 mod error;
+pub mod helpers;
+pub mod liveness;
+pub mod migrations;
 pub mod msg;
+pub mod prices;
+pub mod rewards;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,231 @@
+//! Liveness tracks for every registered operator that has voted whether it submitted
+//! a vote extension in each of the last `window` blocks, and how it voted on the roots
+//! finalized in them. Operators that miss too many blocks of their window are
+//! reported so they can be jailed. Operators that stop voting for a whole window, such
+//! as validators that left the set, are no longer tracked until they vote again.
+
+use cosmwasm_std::{Addr, BlockInfo, Empty, Order, StdResult, Storage, Uint128};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::state::{
+    LivenessConfig, SigningInfo, JAILED, LIVENESS_SET, MISSED_BLOCKS, OPERATORS, SIGNING_INFOS,
+};
+
+/// track records the block for every operator in the liveness set and every
+/// registered operator that signed it. Only the validator set can submit vote
+/// extensions, so registering does not make an address tracked on its own. Jailed
+/// operators and operators that did not vote for a whole window leave the set.
+/// `signers` are the operators that submitted a vote extension in the block and
+/// `finalized` holds, for every root finalized in the block, whether each operator
+/// that voted for its chain voted for it. Returns the operators that missed more
+/// blocks of their window than allowed, whose windows start over.
+pub fn track(
+    storage: &mut dyn Storage,
+    config: &LivenessConfig,
+    block: &BlockInfo,
+    signers: &BTreeSet<Addr>,
+    finalized: &[BTreeMap<Addr, bool>],
+) -> StdResult<Vec<Addr>> {
+    let mut operators: BTreeSet<Addr> = LIVENESS_SET
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for signer in signers {
+        if OPERATORS.has(storage, signer.clone()) {
+            operators.insert(signer.clone());
+        }
+    }
+    let max_missed = config.window
+        - Uint128::from(config.window)
+            .mul_floor(config.min_signed)
+            .u128() as u64;
+
+    let mut offenders = vec![];
+    for operator in operators {
+        if JAILED
+            .may_load(storage, operator.clone())?
+            .is_some_and(|jailed_until| jailed_until > block.time)
+        {
+            LIVENESS_SET.remove(storage, operator);
+            continue;
+        }
+        let mut info = SIGNING_INFOS
+            .may_load(storage, operator.clone())?
+            .unwrap_or_default();
+        if info.window != config.window {
+            reset_window(storage, &operator, &mut info)?;
+            info.window = config.window;
+        }
+        if info.index_offset == 0 {
+            info.start_height = block.height;
+        }
+
+        let index = info.index_offset % config.window;
+        let signed = signers.contains(&operator);
+        let missed_before = MISSED_BLOCKS.has(storage, (operator.clone(), index));
+        if !signed && !missed_before {
+            MISSED_BLOCKS.save(storage, (operator.clone(), index), &Empty {})?;
+            info.missed_blocks += 1;
+        } else if signed && missed_before {
+            MISSED_BLOCKS.remove(storage, (operator.clone(), index));
+            info.missed_blocks -= 1;
+        }
+        info.index_offset += 1;
+        if signed {
+            info.last_voted_height = Some(block.height);
+        }
+
+        for votes in finalized {
+            match votes.get(&operator) {
+                None => info.missed_roots += 1,
+                Some(false) => info.minority_votes += 1,
+                Some(true) => {}
+            }
+        }
+
+        let absent = info
+            .last_voted_height
+            .map_or(true, |height| height + config.window <= block.height);
+        if info.index_offset >= config.window && info.missed_blocks > max_missed {
+            reset_window(storage, &operator, &mut info)?;
+            LIVENESS_SET.remove(storage, operator.clone());
+            offenders.push(operator.clone());
+        } else if absent {
+            reset_window(storage, &operator, &mut info)?;
+            LIVENESS_SET.remove(storage, operator.clone());
+        } else {
+            LIVENESS_SET.save(storage, operator.clone(), &Empty {})?;
+        }
+        SIGNING_INFOS.save(storage, operator, &info)?;
+    }
+    Ok(offenders)
+}
+
+/// reset_window forgets the blocks the operator missed so its window starts over.
+fn reset_window(
+    storage: &mut dyn Storage,
+    operator: &Addr,
+    info: &mut SigningInfo,
+) -> StdResult<()> {
+    let missed: Vec<u64> = MISSED_BLOCKS
+        .prefix(operator.clone())
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for index in missed {
+        MISSED_BLOCKS.remove(storage, (operator.clone(), index));
+    }
+    info.index_offset = 0;
+    info.missed_blocks = 0;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{KeyType, OperatorInfo, OperatorKey};
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+    use cosmwasm_std::{Binary, Decimal};
+
+    #[test]
+    fn missed_blocks_slide_out_of_the_window() {
+        let mut storage = MockStorage::new();
+        let operator = Addr::unchecked("op");
+        let info = OperatorInfo {
+            key: OperatorKey {
+                key_type: KeyType::Ed25519,
+                pubkey: Binary::from([0u8; 32].as_slice()),
+            },
+            pending_key: None,
+            commission: Decimal::zero(),
+        };
+        OPERATORS
+            .save(&mut storage, operator.clone(), &info)
+            .unwrap();
+        let config = LivenessConfig {
+            window: 3,
+            min_signed: Decimal::percent(67),
+            slash_fraction: Decimal::zero(),
+            jail_duration: 0,
+        };
+        let mut block = mock_env().block;
+        let signed = BTreeSet::from([operator.clone()]);
+
+        // operators are only tracked once they vote
+        let offenders = track(&mut storage, &config, &block, &BTreeSet::new(), &[]).unwrap();
+        assert!(offenders.is_empty());
+        assert!(!SIGNING_INFOS.has(&storage, operator.clone()));
+
+        // one block of every three may be missed, the first miss slides out of the window
+        for signers in [
+            signed.clone(),
+            BTreeSet::new(),
+            signed.clone(),
+            signed.clone(),
+            signed.clone(),
+            BTreeSet::new(),
+        ] {
+            let offenders = track(&mut storage, &config, &block, &signers, &[]).unwrap();
+            assert!(offenders.is_empty());
+            block.height += 1;
+        }
+        let info = SIGNING_INFOS.load(&storage, operator.clone()).unwrap();
+        assert_eq!(info.missed_blocks, 1);
+        assert_eq!(info.last_voted_height, Some(block.height - 2));
+
+        let offenders = track(&mut storage, &config, &block, &BTreeSet::new(), &[]).unwrap();
+        assert_eq!(offenders, vec![operator.clone()]);
+        let info = SIGNING_INFOS.load(&storage, operator.clone()).unwrap();
+        assert_eq!((info.index_offset, info.missed_blocks), (0, 0));
+        assert!(!LIVENESS_SET.has(&storage, operator));
+    }
+
+    #[test]
+    fn absent_operators_are_no_longer_tracked() {
+        let mut storage = MockStorage::new();
+        let operator = Addr::unchecked("op");
+        let info = OperatorInfo {
+            key: OperatorKey {
+                key_type: KeyType::Ed25519,
+                pubkey: Binary::from([0u8; 32].as_slice()),
+            },
+            pending_key: None,
+            commission: Decimal::zero(),
+        };
+        OPERATORS
+            .save(&mut storage, operator.clone(), &info)
+            .unwrap();
+        // missing blocks is never an offence
+        let config = LivenessConfig {
+            window: 3,
+            min_signed: Decimal::zero(),
+            slash_fraction: Decimal::zero(),
+            jail_duration: 0,
+        };
+        let mut block = mock_env().block;
+        let signed = BTreeSet::from([operator.clone()]);
+
+        track(&mut storage, &config, &block, &signed, &[]).unwrap();
+        for _ in 0..2 {
+            block.height += 1;
+            track(&mut storage, &config, &block, &BTreeSet::new(), &[]).unwrap();
+            assert!(LIVENESS_SET.has(&storage, operator.clone()));
+        }
+        // a whole window passed since the operator last voted
+        block.height += 1;
+        let offenders = track(&mut storage, &config, &block, &BTreeSet::new(), &[]).unwrap();
+        assert!(offenders.is_empty());
+        assert!(!LIVENESS_SET.has(&storage, operator.clone()));
+        let info = SIGNING_INFOS.load(&storage, operator.clone()).unwrap();
+        assert_eq!((info.index_offset, info.missed_blocks), (0, 0));
+        assert!(MISSED_BLOCKS.prefix(operator.clone()).is_empty(&storage));
+
+        // later blocks leave its record alone until it votes again
+        block.height += 1;
+        track(&mut storage, &config, &block, &BTreeSet::new(), &[]).unwrap();
+        assert_eq!(
+            SIGNING_INFOS.load(&storage, operator.clone()).unwrap(),
+            info
+        );
+        track(&mut storage, &config, &block, &signed, &[]).unwrap();
+        assert!(LIVENESS_SET.has(&storage, operator));
+    }
+}
@@ -0,0 +1,234 @@
+use cosmwasm_std::{Addr, Coin, DepsMut, Env, Order, StdResult, Uint128};
+use semver::Version;
+
+use crate::contract::{initialize, CACHE_SIZE};
+use crate::error::{ContractError, ContractResult};
+use crate::helpers::add_coins;
+use crate::msg::{InstantiateMsg, MigrateMsg};
+use crate::rewards;
+use crate::state::{
+    FinalizedRoot, RootCursor, CONFIG, ROOTS, ROOT_CURSORS, ROOT_INDEX, STAKE_MAP, TOTAL_STAKE,
+};
+
+/// A migration step upgrades state written by versions older than the one it is
+/// listed under. It is given the version the contract is migrated from.
+pub type MigrationStep = fn(DepsMut, &Env, &Version, &MigrateMsg) -> ContractResult<()>;
+
+/// Every migration step, ordered by the version that introduced it.
+pub const MIGRATIONS: &[(&str, MigrationStep)] = &[("0.2.0", v0_2_0)];
+
+/// State layout of versions before 0.2.0.
+pub mod v0_1 {
+    use cosmwasm_std::Binary;
+    use cw_storage_plus::Map;
+    use serde::{Deserialize, Serialize};
+
+    pub const MERKLE_ROOTS: Map<String, ChainHashes> = Map::new("chain_hashes_map");
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ChainHashes {
+        pub chain_id: String,
+        pub hashes: Vec<Binary>,
+        pub max_size: usize,
+    }
+}
+
+/// v0_2_0 sets up the configuration and ownership that 0.1 did not store, tracks the
+/// total stake and the reward shares of every staker, and moves the cached roots
+/// into the indexed root storage. Nothing is known about how the cached roots were
+/// finalized, so they are recorded as finalized by the migration, at source height
+/// zero and without attesting power. Their chains have to be registered before they
+/// accept new roots.
+fn v0_2_0(mut deps: DepsMut, env: &Env, from: &Version, msg: &MigrateMsg) -> ContractResult<()> {
+    if !CONFIG.exists(deps.storage) {
+        let Some(
+            config @ InstantiateMsg {
+                owner: Some(owner), ..
+            },
+        ) = &msg.config
+        else {
+            return Err(ContractError::MissingMigrationConfig {
+                from: from.to_string(),
+            });
+        };
+        let owner = deps.api.addr_validate(owner)?;
+        initialize(deps.branch(), owner, config.clone())?;
+    }
+
+    let stakes: Vec<(Addr, Vec<Coin>)> = STAKE_MAP
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    let total_stake = stakes
+        .iter()
+        .try_fold(Vec::new(), |total, (_, coins)| add_coins(total, coins))?;
+    TOTAL_STAKE.save(deps.storage, &total_stake)?;
+    let config = CONFIG.load(deps.storage)?;
+    for (staker, coins) in &stakes {
+        rewards::update_shares(deps.storage, &config, staker, staker, coins)?;
+    }
+
+    let legacy_roots: Vec<(String, v0_1::ChainHashes)> = v0_1::MERKLE_ROOTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (chain_id, chain_hashes) in legacy_roots {
+        // only the newest roots fit into the default retention
+        let skipped = chain_hashes
+            .hashes
+            .len()
+            .saturating_sub(CACHE_SIZE as usize);
+        let mut next_seq = 0;
+        for root in chain_hashes.hashes.into_iter().skip(skipped) {
+            ROOTS.save(
+                deps.storage,
+                (chain_id.clone(), next_seq),
+                &FinalizedRoot {
+                    root: root.clone(),
+                    source_height: 0,
+                    finalized_height: env.block.height,
+                    finalized_at: env.block.time,
+                    power: Uint128::zero(),
+                },
+            )?;
+            ROOT_INDEX.save(deps.storage, (chain_id.clone(), root.to_vec()), &next_seq)?;
+            next_seq += 1;
+        }
+        ROOT_CURSORS.save(
+            deps.storage,
+            chain_id.clone(),
+            &RootCursor {
+                first_seq: 0,
+                next_seq,
+            },
+        )?;
+        v0_1::MERKLE_ROOTS.remove(deps.storage, chain_id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{migrate, query, CONTRACT_NAME, CONTRACT_VERSION};
+    use crate::state::{PowerSource, OWNERSHIP};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{coin, Binary, Decimal, Deps, Storage};
+    use cw2::{get_contract_version, set_contract_version};
+    use std::collections::BTreeMap;
+
+    /// Writes state the way 0.1.0 stored it.
+    fn load_v0_1_fixture(storage: &mut dyn Storage) {
+        set_contract_version(storage, CONTRACT_NAME, "0.1.0").unwrap();
+        let hashes: Vec<String> = (0..7)
+            .map(|index| format!("\"{}\"", Binary::from(format!("root{index}").as_bytes())))
+            .collect();
+        storage.set(
+            &v0_1::MERKLE_ROOTS.key("foo".to_string()),
+            format!(
+                r#"{{"chain_id":"foo","hashes":[{}],"max_size":7}}"#,
+                hashes.join(",")
+            )
+            .as_bytes(),
+        );
+        storage.set(
+            &v0_1::MERKLE_ROOTS.key("bar".to_string()),
+            br#"{"chain_id":"bar","hashes":["YmFy"],"max_size":6}"#,
+        );
+        storage.set(
+            &STAKE_MAP.key(Addr::unchecked("alice")),
+            br#"[{"denom":"untrn","amount":"100"},{"denom":"uatom","amount":"5"}]"#,
+        );
+        storage.set(
+            &STAKE_MAP.key(Addr::unchecked("bob")),
+            br#"[{"denom":"untrn","amount":"50"}]"#,
+        );
+    }
+
+    fn migrate_msg(owner: Option<&str>) -> MigrateMsg {
+        MigrateMsg {
+            config: Some(InstantiateMsg {
+                owner: owner.map(str::to_string),
+                unbonding_period: 60,
+                power_source: PowerSource::VotePower,
+                denom_weights: BTreeMap::new(),
+                default_threshold: Decimal::from_ratio(2u128, 3u128),
+            }),
+        }
+    }
+
+    #[test]
+    fn migrate_from_v0_1() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        load_v0_1_fixture(deps.as_mut().storage);
+
+        let err = migrate(deps.as_mut(), env.clone(), MigrateMsg { config: None }).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MissingMigrationConfig {
+                from: "0.1.0".to_string()
+            }
+        );
+        let err = migrate(deps.as_mut(), env.clone(), migrate_msg(None)).unwrap_err();
+        assert!(matches!(err, ContractError::MissingMigrationConfig { .. }));
+
+        migrate(deps.as_mut(), env.clone(), migrate_msg(Some("owner"))).unwrap();
+        assert_eq!(
+            get_contract_version(deps.as_ref().storage).unwrap().version,
+            CONTRACT_VERSION
+        );
+        assert_eq!(
+            OWNERSHIP.load(deps.as_ref().storage).unwrap().owner,
+            Some(Addr::unchecked("owner"))
+        );
+        assert_eq!(
+            TOTAL_STAKE.load(deps.as_ref().storage).unwrap(),
+            vec![coin(150, "untrn"), coin(5, "uatom")]
+        );
+
+        // the oldest root of "foo" did not fit the default retention
+        let lookup = |deps: Deps, chain_id: &str, root: &str| {
+            query::lookup_root(
+                deps,
+                mock_env(),
+                chain_id.to_string(),
+                Binary::from(root.as_bytes()),
+            )
+        };
+        assert!(lookup(deps.as_ref(), "foo", "root0").is_err());
+        let response = lookup(deps.as_ref(), "foo", "root1").unwrap();
+        assert_eq!(response.age, 6);
+        assert_eq!(response.finalized_height, env.block.height);
+        assert_eq!(response.power, Uint128::zero());
+        assert_eq!(lookup(deps.as_ref(), "foo", "root6").unwrap().age, 1);
+        assert_eq!(lookup(deps.as_ref(), "bar", "bar").unwrap().age, 1);
+        assert!(v0_1::MERKLE_ROOTS.is_empty(deps.as_ref().storage));
+
+        // migrating again from the current version does not touch the state
+        let res = migrate(deps.as_mut(), env, MigrateMsg { config: None }).unwrap();
+        assert_eq!(res.attributes[1].value, CONTRACT_VERSION);
+        assert_eq!(lookup(deps.as_ref(), "foo", "root6").unwrap().age, 1);
+    }
+
+    #[test]
+    fn migrate_rejects_downgrades_and_other_contracts() {
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { config: None }).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MigrationDowngrade {
+                from: "99.0.0".to_string(),
+                to: CONTRACT_VERSION.to_string(),
+            }
+        );
+
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { config: None }).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::WrongContract {
+                contract: "crates.io:cw20-base".to_string()
+            }
+        );
+    }
+}
@@ -1,51 +1,579 @@
-use bincode::deserialize;
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Binary;
+use crate::state::{
+    Attestation, ChainConfig, ChainFamily, ChainInfo, Config, Delegation, FinalizedRoot,
+    HashFunction, KeyType, LivenessConfig, OperatorKey, Ownership, PendingKey, PowerSource,
+    Retention, RewardsConfig, RootKind, RootTally, SigningInfo, SlashingConfig, SourceRoot,
+    Strategy, Unbonding,
+};
+use aggregator::aggregator::{LookupHashResponse, LookupRootResponse};
+use bincode::{deserialize, DefaultOptions, Options};
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{
+    to_json_vec, Addr, Binary, Coin, Decimal, StdError, StdResult, Timestamp, Uint128,
+};
+use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 
 #[cw_serde]
-pub struct InstantiateMsg {}
+pub struct InstantiateMsg {
+    /// Owner of the contract, defaults to the sender.
+    pub owner: Option<String>,
+    /// Number of seconds unbonded stake stays locked before it can be claimed.
+    pub unbonding_period: u64,
+    pub power_source: PowerSource,
+    pub denom_weights: BTreeMap<String, Decimal>,
+    pub default_threshold: Decimal,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Configuration to initialize the contract with. Required when migrating from a
+    /// version that did not store one.
+    pub config: Option<InstantiateMsg>,
+}
 
 #[cw_serde]
 pub struct SudoMsg {
     pub data: Vec<GenericVE>,
+    /// Total voting power of the validator set, including validators that did not
+    /// submit a vote extension. When omitted, the power of the received vote
+    /// extensions is used. Ignored when power is derived from stake.
+    pub total_power: Option<u64>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
+    /// TransferOwnership offers ownership of the contract to `new_owner`, who has to
+    /// accept it. Replaces any pending offer. Owner only.
+    TransferOwnership {
+        new_owner: String,
+    },
+    /// AcceptOwnership completes a pending ownership transfer. Pending owner only.
+    AcceptOwnership {},
+    /// RenounceOwnership leaves the contract without an owner for good. Owner only.
+    RenounceOwnership {},
+    /// SetPaused stops or resumes accepting roots for every chain. Owner only.
+    SetPaused {
+        paused: bool,
+    },
     Stake {},
-    SubmitRoot { chain_id: String, root: Binary },
+    /// Unbond moves `amount` out of the sender's stake into the unbonding queue.
+    Unbond {
+        amount: Vec<Coin>,
+    },
+    /// ClaimUnbonded sends every matured unbonding and undelegation entry back to the
+    /// sender.
+    ClaimUnbonded {},
+    /// Delegate bonds the sent funds to `operator`, adding to its voting power. The
+    /// delegation is slashed along with the operator.
+    Delegate {
+        operator: String,
+    },
+    /// Undelegate moves `amount` of the sender's delegation to `operator` into
+    /// unbonding. It can still be slashed until it is released.
+    Undelegate {
+        operator: String,
+        amount: Vec<Coin>,
+    },
+    /// RegisterOperator sets the key the sender signs its votes with. The first key
+    /// is active right away, later keys replace it once the key rotation delay has
+    /// passed.
+    RegisterOperator {
+        pubkey: Binary,
+        key_type: KeyType,
+    },
+    /// SubmitEvidence proves that `operator` signed two different roots for the same
+    /// chain and height. The operator is slashed and jailed, and the sender receives
+    /// the reporter bounty.
+    SubmitEvidence {
+        operator: String,
+        vote_a: SignedVote,
+        vote_b: SignedVote,
+    },
+    /// FundRewards adds the sent funds, in the reward denom, to the reward pool.
+    FundRewards {},
+    /// ClaimRewards sends the rewards the sender earned with every operator it stakes
+    /// with.
+    ClaimRewards {},
+    /// Reweight recomputes the reward shares of up to `limit` stakes and delegations
+    /// after the denom weights changed. Rewards keep accruing under the previous
+    /// weights until the shares of a stake are recomputed. Anyone can call it until
+    /// every share is up to date.
+    Reweight {
+        limit: Option<u32>,
+    },
+    /// SetCommission sets the fraction of its delegators' rewards the sender keeps.
+    /// Registered operators only.
+    SetCommission {
+        rate: Decimal,
+    },
+    /// SubmitRoot confirms a root of a chain outside of vote aggregation. The root is
+    /// accepted once `threshold` of the chain's submitters have submitted it. Only
+    /// allowed for submitters of the chain.
+    SubmitRoot {
+        chain_id: String,
+        root: Binary,
+        /// Height of the source chain block the root was taken from.
+        height: u64,
+    },
+    /// RegisterChain adds a chain to the registry so roots are accepted for it.
+    /// Admin only.
+    RegisterChain {
+        chain_id: String,
+        family: ChainFamily,
+        caip2: String,
+        hash_function: HashFunction,
+        root_kind: RootKind,
+        /// Overrides the default retention for the chain.
+        retention: Option<Retention>,
+    },
+    /// SetChainPaused stops or resumes accepting new roots for a chain. Admin only.
+    SetChainPaused {
+        chain_id: String,
+        paused: bool,
+    },
+    /// DeregisterChain removes a chain along with its configuration, submitters and
+    /// roots. Admin only.
+    DeregisterChain {
+        chain_id: String,
+    },
+    /// UpdateConfig overwrites every provided field of the global config. Admin only.
+    UpdateConfig(ConfigUpdate),
+    /// SetChainThreshold sets the quorum threshold of a single chain, or clears the
+    /// override when `threshold` is `None`. Admin only.
+    SetChainThreshold {
+        chain_id: String,
+        threshold: Option<Decimal>,
+    },
+    /// SetChainStrategy selects how votes for a single chain are aggregated, or resets
+    /// it to the default when `strategy` is `None`. Admin only.
+    SetChainStrategy {
+        chain_id: String,
+        strategy: Option<Strategy>,
+    },
+    /// SetChainVoteWindow sets for how many blocks votes for a single chain keep
+    /// counting, or resets it to the default when `vote_window` is `None`. Admin only.
+    SetChainVoteWindow {
+        chain_id: String,
+        vote_window: Option<u64>,
+    },
+    /// SetChainSubmitters replaces the addresses allowed to submit roots for a single
+    /// chain. An empty list disables submitted roots for the chain. Admin only.
+    SetChainSubmitters {
+        chain_id: String,
+        submitters: Vec<String>,
+        threshold: u64,
+    },
+    /// SetChainRetention sets how long the roots of a single chain are kept, or resets
+    /// it to the default when `retention` is `None`. Admin only.
+    SetChainRetention {
+        chain_id: String,
+        retention: Option<Retention>,
+    },
+}
+
+/// ConfigUpdate holds the fields of the global config to overwrite, fields left
+/// `None` keep their value.
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub unbonding_period: Option<u64>,
+    pub power_source: Option<PowerSource>,
+    /// Changing the weights leaves the reward shares of existing stakes to be
+    /// recomputed through `ExecuteMsg::Reweight`.
+    pub denom_weights: Option<BTreeMap<String, Decimal>>,
+    pub default_threshold: Option<Decimal>,
+    pub default_strategy: Option<Strategy>,
+    pub default_vote_window: Option<u64>,
+    pub default_retention: Option<Retention>,
+    pub slashing: Option<SlashingConfig>,
+    pub key_rotation_delay: Option<u64>,
+    pub rewards: Option<RewardsConfig>,
+    pub liveness: Option<LivenessConfig>,
+    pub record_attestations: Option<bool>,
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(LookupHashResponse)]
+    LookupHash { chain_id: String, hash: Binary },
+
+    #[returns(LookupRootResponse)]
+    LookupRoot { chain_id: String, hash: Binary },
+
+    #[returns(PendingUnbondingsResponse)]
+    PendingUnbondings { address: String },
+
+    #[returns(ConfigResponse)]
+    Config {},
+
+    #[returns(Ownership)]
+    Ownership {},
+
+    #[returns(PendingVotesResponse)]
+    PendingVotes { chain_id: String },
+
+    #[returns(SubmittersResponse)]
+    Submitters { chain_id: String },
+
+    #[returns(ChainInfoResponse)]
+    ChainInfo { chain_id: String },
+
+    #[returns(ListChainsResponse)]
+    ListChains {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(RootResponse)]
+    LatestRoot { chain_id: String },
+
+    /// RootAttestation returns the votes a stored root was finalized with. Only
+    /// available for roots finalized while `record_attestations` was set.
+    #[returns(Attestation)]
+    RootAttestation { chain_id: String, root: Binary },
+
+    #[returns(ListRootsResponse)]
+    ListRoots {
+        chain_id: String,
+        /// Sequence number to start after.
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(StakeResponse)]
+    StakeOf { address: String },
+
+    #[returns(ListStakersResponse)]
+    ListStakers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(TotalStakeResponse)]
+    TotalStake {},
+
+    /// SimulateAggregation aggregates vote extensions the way `sudo` would at the
+    /// current block, including the votes carried over from earlier blocks, without
+    /// changing any state.
+    #[returns(SimulateAggregationResponse)]
+    SimulateAggregation {
+        data: Vec<GenericVE>,
+        /// Total voting power of the validator set, as in `SudoMsg`.
+        total_power: Option<u64>,
+    },
+
+    #[returns(OperatorResponse)]
+    Operator { address: String },
+
+    #[returns(PendingRewardsResponse)]
+    PendingRewards { address: String },
+
+    #[returns(SigningInfo)]
+    SigningInfo { operator: String },
+
+    #[returns(PriceResponse)]
+    GetPrice { pair: String },
+
+    #[returns(PricesResponse)]
+    GetPrices { pairs: Vec<String> },
+
+    #[returns(DelegationsResponse)]
+    OperatorDelegations {
+        operator: String,
+        /// Delegator to start after.
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(DelegationsResponse)]
+    DelegatorDelegations {
+        delegator: String,
+        /// Operator to start after.
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub config: Config,
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub struct ChainInfoResponse {
+    pub chain_id: String,
+    pub info: ChainInfo,
+    /// Overrides of the global configuration for the chain.
+    pub config: ChainConfig,
+    /// Retention in effect for the chain, whether overridden or not.
+    pub retention: Retention,
+}
+
+#[cw_serde]
+pub struct ListChainsResponse {
+    pub chains: Vec<ChainInfoResponse>,
+}
+
+#[cw_serde]
+pub struct RootResponse {
+    pub chain_id: String,
+    /// Sequence number of the root among the roots of its chain.
+    pub seq: u64,
+    pub root: FinalizedRoot,
+    /// Number of roots finalized for the chain since this one, including itself.
+    pub age: u64,
+    /// Whether the root exceeds the retention of its chain, expired roots are kept
+    /// until evicted but no longer verify.
+    pub expired: bool,
+}
+
+#[cw_serde]
+pub struct ListRootsResponse {
+    pub roots: Vec<RootResponse>,
+}
+
+#[cw_serde]
+pub struct StakeResponse {
+    pub address: Addr,
+    /// Coins staked by the address itself, not including delegations.
+    pub stake: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct ListStakersResponse {
+    pub stakers: Vec<StakeResponse>,
+}
+
+#[cw_serde]
+pub struct TotalStakeResponse {
+    /// Coins staked and delegated with the contract, not including unbonding coins.
+    pub total_stake: Vec<Coin>,
+    /// Part of `total_stake` held by registered operators that are not jailed, which
+    /// quorum is measured against when power is derived from stake.
+    pub bonded: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct SubmittersResponse {
+    pub submitters: Vec<Addr>,
+    pub threshold: u64,
+}
+
+#[cw_serde]
+pub struct OperatorResponse {
+    pub address: Addr,
+    /// Key the operator currently signs with, if it registered one.
+    pub key: Option<OperatorKey>,
+    /// Key that is about to replace `key`.
+    pub pending_key: Option<PendingKey>,
+    pub stake: Vec<Coin>,
+    /// Sum of the coins delegated to the operator.
+    pub delegated: Vec<Coin>,
+    pub commission: Decimal,
+    /// Time the operator is released from jail at, if it is jailed.
+    pub jailed_until: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    /// Rewards claimable by the address, empty if it has none.
+    pub rewards: Vec<Coin>,
+    /// Rewards left in the pool to be distributed.
+    pub pool: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PriceResponse {
+    pub pair: String,
+    pub price: Uint128,
+    /// Time of the block the price was aggregated at.
+    pub timestamp: Timestamp,
+    /// Height of the block the price was aggregated at.
+    pub height: u64,
+    /// Voting power that reported a price for the pair.
+    pub power: Uint128,
+}
+
+#[cw_serde]
+pub struct PricesResponse {
+    pub prices: Vec<PriceResponse>,
+}
+
+#[cw_serde]
+pub struct SimulateAggregationResponse {
+    pub chains: Vec<ChainAggregationResponse>,
+    /// Chains voted for that are not registered or are paused, every chain while the
+    /// contract is paused.
+    pub ignored_chains: Vec<String>,
+    /// Chains whose tally overflowed and would be skipped.
+    pub tally_overflow: Vec<String>,
+    /// Number of vote extensions that could not be decoded.
+    pub malformed_vote_extensions: u64,
+    /// Operators that submitted more than one vote extension, only the first counts.
+    pub duplicate_operators: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ChainAggregationResponse {
+    pub chain_id: String,
+    /// Power behind every root voted for the chain, highest first.
+    pub roots: Vec<RootTally>,
+    /// Power of every vote that included the chain.
+    pub participating_power: Uint128,
+    /// Power of the full validator set.
+    pub total_power: Uint128,
+    /// Root with the most power, finalized only if the quorum is met.
+    pub winner: Option<SourceRoot>,
+    pub quorum_met: bool,
+}
+
+#[cw_serde]
+pub struct DelegationsResponse {
+    pub delegations: Vec<Delegation>,
+}
+
+#[cw_serde]
+pub struct PendingUnbondingsResponse {
+    pub unbondings: Vec<Unbonding>,
+    pub undelegations: Vec<PendingUndelegation>,
+}
+
+#[cw_serde]
+pub struct PendingUndelegation {
+    pub operator: Addr,
+    pub unbondings: Vec<Unbonding>,
 }
 
 #[cw_serde]
 pub struct Vote {
-    pub roots: BTreeMap<String, Binary>,
+    pub roots: BTreeMap<String, SourceRoot>,
+    /// Oracle prices by currency pair. Votes without prices are encoded without the
+    /// field, as they were before prices were added.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prices: BTreeMap<String, Uint128>,
+}
+
+impl Vote {
+    /// decode reads a bincode encoded vote. Bincode does not support optional trailing
+    /// fields, so a vote that ends after its roots is decoded without prices. Votes from
+    /// before roots carried their source height decode with a height of 0, as unknown.
+    /// Each encoding must span the whole input, so that one can not be mistaken for
+    /// another.
+    pub fn decode(bytes: &[u8]) -> StdResult<Self> {
+        #[derive(serde::Deserialize)]
+        struct RootsVote {
+            roots: BTreeMap<String, SourceRoot>,
+        }
+        #[derive(serde::Deserialize)]
+        struct BinaryRootsVote {
+            roots: BTreeMap<String, Binary>,
+        }
+
+        let options = DefaultOptions::new()
+            .with_fixint_encoding()
+            .reject_trailing_bytes();
+        options
+            .deserialize(bytes)
+            .or_else(|err| {
+                options
+                    .deserialize(bytes)
+                    .map(|vote: RootsVote| Vote {
+                        roots: vote.roots,
+                        prices: BTreeMap::new(),
+                    })
+                    .or_else(|_| {
+                        options
+                            .deserialize(bytes)
+                            .map(|vote: BinaryRootsVote| Vote {
+                                roots: vote
+                                    .roots
+                                    .into_iter()
+                                    .map(|(chain_id, root)| {
+                                        (chain_id, SourceRoot { root, height: 0 })
+                                    })
+                                    .collect(),
+                                prices: BTreeMap::new(),
+                            })
+                    })
+                    .map_err(|_| err)
+            })
+            .map_err(|err| StdError::parse_err("Vote", err.to_string()))
+    }
+}
+
+/// SignedVote is the signature of an operator over its vote for a single chain.
+#[cw_serde]
+pub struct SignedVote {
+    pub chain_id: String,
+    pub root: SourceRoot,
+    /// Signature over `sign_bytes` by a key of the operator.
+    pub signature: Binary,
+}
+
+impl SignedVote {
+    /// sign_bytes is the SHA-256 hash of the JSON encoded single chain `Vote` the
+    /// operator signed.
+    pub fn sign_bytes(&self) -> StdResult<Vec<u8>> {
+        let vote = Vote {
+            roots: BTreeMap::from([(self.chain_id.clone(), self.root.clone())]),
+            prices: BTreeMap::new(),
+        };
+        Ok(Sha256::digest(to_json_vec(&vote)?).to_vec())
+    }
 }
 
 #[cw_serde]
 pub struct VoteExtension {
     pub vote: Vote,
-    pub ve_power: u64,
+    pub ve_power: Uint128,
+    pub operator: Option<String>,
 }
 
 #[cw_serde]
 pub struct GenericVE {
     pub vote: Binary,
     pub ve_power: u64,
+    /// Address of the operator that produced the vote extension. Required for the
+    /// vote to carry any power when the contract derives power from stake, and only
+    /// registered operators do. The contract does not authenticate it: the module
+    /// calling sudo is trusted to set it from the validator that signed the vote
+    /// extension, and is its only source.
+    pub operator: Option<String>,
 }
 
-impl From<GenericVE> for VoteExtension {
-    fn from(value: GenericVE) -> Self {
-        let vote = deserialize(value.vote.as_ref()).unwrap();
-        VoteExtension {
+impl TryFrom<GenericVE> for VoteExtension {
+    type Error = StdError;
+
+    fn try_from(value: GenericVE) -> Result<Self, Self::Error> {
+        let vote = Vote::decode(value.vote.as_ref())?;
+        Ok(VoteExtension {
             vote,
-            ve_power: value.ve_power,
-        }
+            ve_power: value.ve_power.into(),
+            operator: value.operator,
+        })
     }
 }
 
-impl From<Binary> for VoteExtension {
-    fn from(value: Binary) -> Self {
-        deserialize(value.as_ref()).unwrap()
+impl TryFrom<Binary> for VoteExtension {
+    type Error = StdError;
+
+    fn try_from(value: Binary) -> Result<Self, Self::Error> {
+        deserialize(value.as_ref())
+            .map_err(|err| StdError::parse_err("VoteExtension", err.to_string()))
     }
 }
+
+#[cw_serde]
+pub struct PendingVotesResponse {
+    pub roots: Vec<PendingRoot>,
+}
+
+#[cw_serde]
+pub struct PendingRoot {
+    pub root: SourceRoot,
+    /// Power of the carried over votes for the root.
+    pub power: Uint128,
+    pub operators: Vec<String>,
+}
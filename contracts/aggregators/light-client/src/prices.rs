@@ -0,0 +1,95 @@
+//! Prices aggregates the oracle prices operators report in their vote extensions
+//! into a single stake-weighted median per currency pair.
+
+use cosmwasm_std::{BlockInfo, Decimal, StdResult, Storage, Uint128};
+use std::collections::BTreeMap;
+
+use crate::aggregation::meets_threshold;
+use crate::state::{PriceInfo, PRICES};
+
+/// weighted_median returns the lowest price that at least half of the power voted for
+/// or below. Votes are `(price, power)` pairs, votes without power are ignored.
+pub fn weighted_median(mut votes: Vec<(Uint128, Uint128)>) -> StdResult<Option<Uint128>> {
+    votes.retain(|(_, power)| !power.is_zero());
+    votes.sort();
+    let total_power = votes
+        .iter()
+        .try_fold(Uint128::zero(), |total, (_, power)| {
+            total.checked_add(*power)
+        })?;
+    let mut power_below = Uint128::zero();
+    for (price, power) in votes {
+        power_below = power_below.checked_add(power)?;
+        if power_below.full_mul(2u128) >= total_power.into() {
+            return Ok(Some(price));
+        }
+    }
+    Ok(None)
+}
+
+/// update saves the median price of every pair for which the power that reported a
+/// price holds `threshold` of `total_power`, and returns the updated pairs. Pairs
+/// that fall short keep their last price.
+pub fn update(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    votes: BTreeMap<String, Vec<(Uint128, Uint128)>>,
+    total_power: Uint128,
+    threshold: Decimal,
+) -> StdResult<Vec<String>> {
+    let mut updated = vec![];
+    for (pair, votes) in votes {
+        let power = votes
+            .iter()
+            .try_fold(Uint128::zero(), |total, (_, power)| {
+                total.checked_add(*power)
+            })?;
+        if !meets_threshold(power, total_power, threshold) {
+            continue;
+        }
+        let Some(price) = weighted_median(votes)? else {
+            continue;
+        };
+        let info = PriceInfo {
+            price,
+            timestamp: block.time,
+            height: block.height,
+            power,
+        };
+        PRICES.save(storage, pair.clone(), &info)?;
+        updated.push(pair);
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(votes: &[(u128, u128)]) -> Vec<(Uint128, Uint128)> {
+        votes
+            .iter()
+            .map(|(price, power)| (Uint128::new(*price), Uint128::new(*power)))
+            .collect()
+    }
+
+    #[test]
+    fn median_is_weighted_by_power() {
+        assert_eq!(weighted_median(vec![]).unwrap(), None);
+        assert_eq!(weighted_median(votes(&[(100, 0)])).unwrap(), None);
+        assert_eq!(
+            weighted_median(votes(&[(300, 1), (100, 1), (200, 1)])).unwrap(),
+            Some(Uint128::new(200))
+        );
+        // a single heavy voter outweighs the others
+        assert_eq!(
+            weighted_median(votes(&[(100, 1), (200, 1), (900, 5)])).unwrap(),
+            Some(Uint128::new(900))
+        );
+        // an exact split goes to the lower price
+        assert_eq!(
+            weighted_median(votes(&[(200, 3), (100, 3)])).unwrap(),
+            Some(Uint128::new(100))
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Rewards are paid out of the reward pool to the operators that voted for finalized
+//! roots. Operators keep their commission and share the rest with everyone staking
+//! with them through a reward per share accumulator, so neither distributing nor
+//! claiming rewards iterates over the stakers of an operator.
+
+use cosmwasm_std::{Addr, Coin, Decimal, Decimal256, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::Bound;
+use std::collections::BTreeMap;
+
+use crate::error::{ContractError, ContractResult};
+use crate::helpers::weighted_power;
+use crate::state::{
+    Config, OperatorRewards, RewardPosition, Reweight, DELEGATIONS, DELEGATION_SLASH_FACTORS,
+    OPERATORS, OPERATOR_REWARDS, REWARD_POOL, REWARD_POSITIONS, REWEIGHT, SLASH_FACTORS, STAKE_MAP,
+};
+
+/// accrued returns the rewards `position` earned since it was last settled.
+fn accrued(position: &RewardPosition, rewards: &OperatorRewards) -> StdResult<Uint128> {
+    let growth = rewards.reward_per_share - position.reward_per_share_paid;
+    let accrued = Decimal256::from_ratio(position.shares, 1u128).checked_mul(growth)?;
+    Ok(accrued.to_uint_floor().try_into()?)
+}
+
+/// settle credits the rewards accrued by the position of `staker` with `operator` and
+/// sets its shares to `shares`.
+pub fn settle(
+    storage: &mut dyn Storage,
+    staker: &Addr,
+    operator: &Addr,
+    shares: Uint128,
+) -> StdResult<()> {
+    let mut rewards = OPERATOR_REWARDS
+        .may_load(storage, operator.clone())?
+        .unwrap_or_default();
+    let key = (staker.clone(), operator.clone());
+    let mut position = REWARD_POSITIONS
+        .may_load(storage, key.clone())?
+        .unwrap_or_default();
+
+    position.pending = position
+        .pending
+        .checked_add(accrued(&position, &rewards)?)?;
+    position.reward_per_share_paid = rewards.reward_per_share;
+    rewards.total_shares = rewards
+        .total_shares
+        .checked_sub(position.shares)?
+        .checked_add(shares)?;
+    position.shares = shares;
+
+    if position.shares.is_zero() && position.pending.is_zero() {
+        REWARD_POSITIONS.remove(storage, key);
+    } else {
+        REWARD_POSITIONS.save(storage, key, &position)?;
+    }
+    OPERATOR_REWARDS.save(storage, operator.clone(), &rewards)
+}
+
+/// update_shares settles the position of `staker` with `operator` and sets its shares
+/// from the weighted power of `coins`, the stake it now holds with the operator.
+/// Shares count stake as it was before any slash of the operator, so slashes, which
+/// hit every staker of the operator alike, leave the shares as they are.
+pub fn update_shares(
+    storage: &mut dyn Storage,
+    config: &Config,
+    staker: &Addr,
+    operator: &Addr,
+    coins: &[Coin],
+) -> ContractResult<()> {
+    let overflow = || ContractError::VotePowerOverflow {
+        operator: operator.to_string(),
+    };
+    let power = weighted_power(coins, &config.denom_weights).ok_or_else(overflow)?;
+    let slash_factor = SLASH_FACTORS
+        .may_load(storage, operator.clone())?
+        .unwrap_or_default();
+    let shares = Decimal256::from_ratio(power, 1u128)
+        .checked_div(slash_factor.factor)
+        .map_err(|_| overflow())?
+        .to_uint_floor()
+        .try_into()
+        .map_err(|_| overflow())?;
+    Ok(settle(storage, staker, operator, shares)?)
+}
+
+/// reweight updates the shares of up to `limit` self-stakes and delegations to the
+/// denom weights of `config`, settling the rewards they accrued under the previous
+/// ones. It continues from where the pending recomputation in `REWEIGHT` stopped and
+/// returns whether every share is up to date.
+pub fn reweight(storage: &mut dyn Storage, config: &Config, limit: usize) -> ContractResult<bool> {
+    let Some(mut position) = REWEIGHT.may_load(storage)? else {
+        return Ok(true);
+    };
+    let mut remaining = limit;
+    let mut done = false;
+    if let Reweight::Stakes { start_after } = &position {
+        let stakes: Vec<(Addr, Vec<Coin>)> = STAKE_MAP
+            .range(
+                storage,
+                start_after.clone().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(remaining)
+            .collect::<StdResult<_>>()?;
+        if stakes.len() < remaining {
+            position = Reweight::Delegations { start_after: None };
+        } else if let Some((staker, _)) = stakes.last() {
+            position = Reweight::Stakes {
+                start_after: Some(staker.clone()),
+            };
+        }
+        remaining -= stakes.len();
+        for (staker, coins) in stakes {
+            update_shares(storage, config, &staker, &staker, &coins)?;
+        }
+    }
+    if let Reweight::Delegations { start_after } = &position {
+        let delegations: Vec<((Addr, Addr), Vec<Coin>)> = DELEGATIONS
+            .range(
+                storage,
+                start_after.clone().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(remaining)
+            .collect::<StdResult<_>>()?;
+        if delegations.len() < remaining {
+            done = true;
+        } else if let Some((key, _)) = delegations.last() {
+            position = Reweight::Delegations {
+                start_after: Some(key.clone()),
+            };
+        }
+        for ((operator, delegator), coins) in delegations {
+            let slash_factor = SLASH_FACTORS
+                .may_load(storage, operator.clone())?
+                .unwrap_or_default();
+            let recorded = DELEGATION_SLASH_FACTORS
+                .may_load(storage, (operator.clone(), delegator.clone()))?
+                .unwrap_or_default();
+            let coins = slash_factor.scale(&recorded, coins);
+            update_shares(storage, config, &delegator, &operator, &coins)?;
+        }
+    }
+    if done {
+        REWEIGHT.remove(storage);
+    } else {
+        REWEIGHT.save(storage, &position)?;
+    }
+    Ok(done)
+}
+
+/// distribute pays the rewards of a block out of the reward pool, split between
+/// operators by the power they voted for finalized roots with. Returns the amount
+/// distributed.
+pub fn distribute(
+    storage: &mut dyn Storage,
+    config: &Config,
+    operator_power: BTreeMap<Addr, Uint128>,
+) -> StdResult<Uint128> {
+    let Some(rewards_config) = &config.rewards else {
+        return Ok(Uint128::zero());
+    };
+    let pool = REWARD_POOL.may_load(storage)?.unwrap_or_default();
+    let emission = pool.min(rewards_config.per_block);
+    let total_power = operator_power
+        .values()
+        .try_fold(Uint128::zero(), |total, power| total.checked_add(*power))?;
+    if emission.is_zero() || total_power.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let mut distributed = Uint128::zero();
+    for (operator, power) in operator_power {
+        let reward = emission.multiply_ratio(power, total_power);
+        if reward.is_zero() {
+            continue;
+        }
+        let commission_rate = OPERATORS
+            .may_load(storage, operator.clone())?
+            .map_or(Decimal::zero(), |operator| operator.commission);
+        let mut rewards = OPERATOR_REWARDS
+            .may_load(storage, operator.clone())?
+            .unwrap_or_default();
+        // an operator without any stake has nobody to share its rewards with
+        let commission = if rewards.total_shares.is_zero() {
+            reward
+        } else {
+            reward.mul_floor(commission_rate)
+        };
+        let shared = reward - commission;
+        if !shared.is_zero() {
+            rewards.reward_per_share = rewards
+                .reward_per_share
+                .checked_add(Decimal256::from_ratio(shared, rewards.total_shares))?;
+            OPERATOR_REWARDS.save(storage, operator.clone(), &rewards)?;
+        }
+        if !commission.is_zero() {
+            // pending rewards do not depend on the accumulator, no need to settle
+            let key = (operator.clone(), operator);
+            let mut position = REWARD_POSITIONS
+                .may_load(storage, key.clone())?
+                .unwrap_or_else(|| RewardPosition {
+                    reward_per_share_paid: rewards.reward_per_share,
+                    ..Default::default()
+                });
+            position.pending = position.pending.checked_add(commission)?;
+            REWARD_POSITIONS.save(storage, key, &position)?;
+        }
+        distributed += reward;
+    }
+    REWARD_POOL.save(storage, &(pool - distributed))?;
+    Ok(distributed)
+}
+
+/// claim settles every position of `staker` and returns the rewards they had pending.
+pub fn claim(storage: &mut dyn Storage, staker: &Addr) -> StdResult<Uint128> {
+    let positions: Vec<(Addr, RewardPosition)> = REWARD_POSITIONS
+        .prefix(staker.clone())
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    let mut claimed = Uint128::zero();
+    for (operator, position) in positions {
+        settle(storage, staker, &operator, position.shares)?;
+        let key = (staker.clone(), operator);
+        let mut position = REWARD_POSITIONS.load(storage, key.clone())?;
+        claimed = claimed.checked_add(position.pending)?;
+        position.pending = Uint128::zero();
+        if position.shares.is_zero() {
+            REWARD_POSITIONS.remove(storage, key);
+        } else {
+            REWARD_POSITIONS.save(storage, key, &position)?;
+        }
+    }
+    Ok(claimed)
+}
+
+/// pending returns the rewards `staker` would receive by claiming now.
+pub fn pending(storage: &dyn Storage, staker: &Addr) -> StdResult<Uint128> {
+    REWARD_POSITIONS
+        .prefix(staker.clone())
+        .range(storage, None, None, Order::Ascending)
+        .try_fold(Uint128::zero(), |total, item| {
+            let (operator, position) = item?;
+            let rewards = OPERATOR_REWARDS
+                .may_load(storage, operator)?
+                .unwrap_or_default();
+            Ok(total
+                .checked_add(position.pending)?
+                .checked_add(accrued(&position, &rewards)?)?)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{
+        LivenessConfig, PowerSource, Retention, RewardsConfig, SlashingConfig, Strategy,
+    };
+    use cosmwasm_std::testing::MockStorage;
+
+    fn config() -> Config {
+        Config {
+            unbonding_period: 0,
+            power_source: PowerSource::Stake,
+            denom_weights: BTreeMap::new(),
+            default_threshold: Decimal::one(),
+            default_strategy: Strategy::default(),
+            default_vote_window: 1,
+            default_retention: Retention {
+                max_roots: 1,
+                max_age_seconds: None,
+                max_age_blocks: None,
+            },
+            slashing: SlashingConfig {
+                slash_fraction: Decimal::zero(),
+                reporter_bounty: Decimal::zero(),
+                jail_duration: 0,
+            },
+            key_rotation_delay: 0,
+            rewards: Some(RewardsConfig {
+                denom: "reward".to_string(),
+                per_block: Uint128::new(100),
+            }),
+            liveness: LivenessConfig {
+                window: 1,
+                min_signed: Decimal::zero(),
+                slash_fraction: Decimal::zero(),
+                jail_duration: 0,
+            },
+            record_attestations: false,
+        }
+    }
+
+    #[test]
+    fn shares_only_earn_rewards_distributed_while_held() {
+        let mut storage = MockStorage::new();
+        let config = config();
+        let operator = Addr::unchecked("operator");
+        let (alice, bob) = (Addr::unchecked("alice"), Addr::unchecked("bob"));
+        REWARD_POOL.save(&mut storage, &Uint128::new(1000)).unwrap();
+        let power = BTreeMap::from([(operator.clone(), Uint128::one())]);
+
+        // without any shares the operator keeps everything
+        distribute(&mut storage, &config, power.clone()).unwrap();
+        assert_eq!(pending(&storage, &operator).unwrap(), Uint128::new(100));
+
+        settle(&mut storage, &alice, &operator, Uint128::new(1)).unwrap();
+        distribute(&mut storage, &config, power.clone()).unwrap();
+        settle(&mut storage, &bob, &operator, Uint128::new(3)).unwrap();
+        distribute(&mut storage, &config, power).unwrap();
+        assert_eq!(pending(&storage, &alice).unwrap(), Uint128::new(125));
+        assert_eq!(pending(&storage, &bob).unwrap(), Uint128::new(75));
+        assert_eq!(REWARD_POOL.load(&storage).unwrap(), Uint128::new(700));
+
+        assert_eq!(claim(&mut storage, &alice).unwrap(), Uint128::new(125));
+        assert_eq!(pending(&storage, &alice).unwrap(), Uint128::zero());
+        settle(&mut storage, &bob, &operator, Uint128::zero()).unwrap();
+        assert_eq!(claim(&mut storage, &bob).unwrap(), Uint128::new(75));
+        assert!(!REWARD_POSITIONS.has(&storage, (bob, operator)));
+    }
+
+    #[test]
+    fn reweight_updates_existing_shares() {
+        let mut storage = MockStorage::new();
+        let mut config = config();
+        config.denom_weights = BTreeMap::from([("stake".to_string(), Decimal::one())]);
+        let operator = Addr::unchecked("operator");
+        let alice = Addr::unchecked("alice");
+        let shares = |storage: &dyn Storage, staker: &Addr| {
+            REWARD_POSITIONS
+                .load(storage, (staker.clone(), operator.clone()))
+                .unwrap()
+                .shares
+        };
+
+        let stake = vec![Coin::new(100, "stake")];
+        STAKE_MAP
+            .save(&mut storage, operator.clone(), &stake)
+            .unwrap();
+        update_shares(&mut storage, &config, &operator, &operator, &stake).unwrap();
+        let delegation = vec![Coin::new(300, "stake"), Coin::new(50, "other")];
+        DELEGATIONS
+            .save(&mut storage, (operator.clone(), alice.clone()), &delegation)
+            .unwrap();
+        update_shares(&mut storage, &config, &alice, &operator, &delegation).unwrap();
+        REWARD_POOL.save(&mut storage, &Uint128::new(1000)).unwrap();
+        let power = BTreeMap::from([(operator.clone(), Uint128::one())]);
+        distribute(&mut storage, &config, power).unwrap();
+
+        config.denom_weights = BTreeMap::from([
+            ("stake".to_string(), Decimal::percent(50)),
+            ("other".to_string(), Decimal::from_ratio(2u128, 1u128)),
+        ]);
+        REWEIGHT
+            .save(&mut storage, &Reweight::Stakes { start_after: None })
+            .unwrap();
+        assert!(!reweight(&mut storage, &config, 1).unwrap());
+        assert_eq!(shares(&storage, &operator), Uint128::new(50));
+        assert_eq!(shares(&storage, &alice), Uint128::new(300));
+        assert!(!reweight(&mut storage, &config, 1).unwrap());
+        assert_eq!(shares(&storage, &alice), Uint128::new(250));
+        assert!(reweight(&mut storage, &config, 1).unwrap());
+        assert!(!REWEIGHT.exists(&storage));
+        assert_eq!(
+            OPERATOR_REWARDS
+                .load(&storage, operator.clone())
+                .unwrap()
+                .total_shares,
+            Uint128::new(300)
+        );
+        // rewards accrued under the previous weights are kept
+        assert_eq!(pending(&storage, &operator).unwrap(), Uint128::new(25));
+        assert_eq!(pending(&storage, &alice).unwrap(), Uint128::new(75));
+    }
+}
@@ -1,14 +1,518 @@
-use cosmwasm_std::{Addr, Binary, Coin};
-use cw_storage_plus::Map;
-use serde::{Deserialize, Serialize};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Decimal256, Empty, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
+use std::collections::BTreeMap;
 
-pub const MERKLE_ROOTS: Map<String, ChainHashes> = Map::new("chain_hashes_map");
+/// The owner of the contract, the only address allowed to administer it.
+pub const OWNERSHIP: Item<Ownership> = Item::new("ownership");
+/// Whether the contract has been paused by its owner. No roots are accepted while
+/// paused.
+pub const PAUSED: Item<bool> = Item::new("paused");
+pub const CONFIG: Item<Config> = Item::new("config");
+/// Chains roots are accepted for.
+pub const CHAINS: Map<String, ChainInfo> = Map::new("chains");
+/// Per-chain overrides of the global configuration.
+pub const CHAIN_CONFIGS: Map<String, ChainConfig> = Map::new("chain_configs");
+/// Roots finalized for each chain, keyed by (chain_id, sequence number).
+pub const ROOTS: Map<(String, u64), FinalizedRoot> = Map::new("roots");
+/// Sequence number of every root in `ROOTS`, keyed by (chain_id, root).
+pub const ROOT_INDEX: Map<(String, Vec<u8>), u64> = Map::new("root_index");
+/// Position of each chain in its ring buffer of roots.
+pub const ROOT_CURSORS: Map<String, RootCursor> = Map::new("root_cursors");
 pub const STAKE_MAP: Map<Addr, Vec<Coin>> = Map::new("stake_map");
-pub const QUARUM: f64 = 2f64 / 3f64;
+/// Coins delegated to operators, keyed by (operator, delegator). Amounts are as of the
+/// last update of the delegation and do not reflect later slashes of the operator.
+pub const DELEGATIONS: Map<(Addr, Addr), Vec<Coin>> = Map::new("delegations");
+/// Sum of the coins delegated to each operator.
+pub const DELEGATED: Map<Addr, Vec<Coin>> = Map::new("delegated");
+/// Undelegated coins that have not been claimed yet, keyed by (operator, delegator)
+/// and ordered by release time. Amounts are as of the last update, like `DELEGATIONS`.
+pub const UNDELEGATIONS: Map<(Addr, Addr), Vec<Unbonding>> = Map::new("undelegations");
+/// Sum of the unclaimed coins undelegated from each operator.
+pub const UNDELEGATED: Map<Addr, Vec<Coin>> = Map::new("undelegated");
+/// Share of its delegations and undelegations each operator kept through its slashes.
+/// Operators that were never slashed have no entry.
+pub const SLASH_FACTORS: Map<Addr, SlashFactor> = Map::new("slash_factors");
+/// Slash factor of the operator as of the last update of the delegation and
+/// undelegations of a delegator, keyed by (operator, delegator).
+pub const DELEGATION_SLASH_FACTORS: Map<(Addr, Addr), SlashFactor> =
+    Map::new("delegation_slash_factors");
+/// Operators each delegator has a delegation or undelegation with, keyed by
+/// (delegator, operator).
+pub const DELEGATOR_OPERATORS: Map<(Addr, Addr), Empty> = Map::new("delegator_operators");
+/// Sum of every entry of `STAKE_MAP` and `DELEGATIONS`.
+pub const TOTAL_STAKE: Item<Vec<Coin>> = Item::new("total_stake");
+/// Stake and delegations of the registered operators that are not jailed, which the
+/// bonded power is made of when power is derived from stake.
+pub const BONDED: Item<Vec<Coin>> = Item::new("bonded");
+/// Rewards not yet distributed to operators, in the reward denom.
+pub const REWARD_POOL: Item<Uint128> = Item::new("reward_pool");
+/// Reward accumulator of each operator.
+pub const OPERATOR_REWARDS: Map<Addr, OperatorRewards> = Map::new("operator_rewards");
+/// Stake each staker earns the rewards of an operator with, keyed by (staker,
+/// operator). Self-stake is keyed by (operator, operator).
+pub const REWARD_POSITIONS: Map<(Addr, Addr), RewardPosition> = Map::new("reward_positions");
+/// Next stake to recompute the reward shares of after the denom weights changed.
+/// Absent once every share is up to date.
+pub const REWEIGHT: Item<Reweight> = Item::new("reweight");
+/// Latest vote of each operator, keyed by (chain_id, operator), for chains whose
+/// votes count over multiple blocks. Operators only have one pending vote per chain so
+/// their power is never counted twice.
+pub const PENDING_VOTES: Map<(String, String), PendingVote> = Map::new("pending_votes");
+/// Index of PENDING_VOTES keyed by (operator, chain_id).
+pub const OPERATOR_PENDING_VOTES: Map<(String, String), Empty> = Map::new("operator_pending_votes");
+/// Addresses allowed to submit roots of a chain outside of vote aggregation.
+/// Chains without an entry do not accept submitted roots.
+pub const SUBMITTERS: Map<String, SubmitterSet> = Map::new("submitters");
+/// Submitters that confirmed a root, keyed by (chain_id, root, source height), until
+/// the root gathers enough confirmations.
+pub const ROOT_SUBMISSIONS: Map<(String, Vec<u8>, u64), Vec<Addr>> = Map::new("root_submissions");
+/// Stake that has been unbonded but not yet claimed, ordered by release time.
+pub const UNBONDING_QUEUE: Map<Addr, Vec<Unbonding>> = Map::new("unbonding_queue");
+/// Keys operators sign their votes with.
+pub const OPERATORS: Map<Addr, OperatorInfo> = Map::new("operators");
+/// Operators jailed for misbehaving, with the time they are released at. Jailed
+/// operators vote with zero power.
+pub const JAILED: Map<Addr, Timestamp> = Map::new("jailed");
+/// Jail terms whose stake is not bonded again yet, keyed by (release time in
+/// nanoseconds, operator).
+pub const JAIL_RELEASES: Map<(u64, Addr), Empty> = Map::new("jail_releases");
+/// Operators whose liveness is tracked. Operators join when they vote and leave when
+/// they are jailed or have not voted for a whole window.
+pub const LIVENESS_SET: Map<Addr, Empty> = Map::new("liveness_set");
+/// Liveness record of each operator.
+pub const SIGNING_INFOS: Map<Addr, SigningInfo> = Map::new("signing_infos");
+/// Blocks of its liveness window each operator missed, keyed by (operator, index in
+/// the window).
+pub const MISSED_BLOCKS: Map<(Addr, u64), Empty> = Map::new("missed_blocks");
+/// Equivocations operators have been slashed for, keyed by (operator, chain_id, source
+/// height), with the time the evidence was handled at.
+pub const EVIDENCE: Map<(Addr, String, u64), Timestamp> = Map::new("evidence");
+/// Latest aggregated price of every currency pair.
+pub const PRICES: Map<String, PriceInfo> = Map::new("prices");
+/// Votes behind every stored root finalized by vote, keyed by (chain_id, root). Only
+/// recorded when `record_attestations` is set.
+pub const ATTESTATIONS: Map<(String, Vec<u8>), Attestation> = Map::new("attestations");
 
-#[derive(Serialize, Deserialize)]
-pub struct ChainHashes {
-    pub chain_id: String,
-    pub hashes: Vec<Binary>,
-    pub max_size: usize,
+#[cw_serde]
+pub struct SubmitterSet {
+    pub submitters: Vec<Addr>,
+    /// Number of submitters that have to submit the same root for it to be accepted.
+    pub threshold: u64,
+}
+
+#[cw_serde]
+pub struct RootCursor {
+    /// Sequence number of the oldest root of the chain still in storage.
+    pub first_seq: u64,
+    /// Sequence number the next root of the chain is stored at.
+    pub next_seq: u64,
+}
+
+/// FinalizedRoot is a root accepted for a chain together with what is known about
+/// how it was accepted.
+#[cw_serde]
+pub struct FinalizedRoot {
+    pub root: Binary,
+    /// Height of the source chain block the root was taken from.
+    pub source_height: u64,
+    /// Height of the block the root was finalized at.
+    pub finalized_height: u64,
+    /// Time of the block the root was finalized at.
+    pub finalized_at: Timestamp,
+    /// Voting power that attested to the root. Zero for submitted roots.
+    pub power: Uint128,
+}
+
+/// SourceRoot is a merkle root of a source chain along with the height of the block
+/// it was taken from. Votes only agree if both match.
+#[cw_serde]
+#[derive(Eq, PartialOrd, Ord)]
+pub struct SourceRoot {
+    pub root: Binary,
+    pub height: u64,
+}
+
+/// Attestation records who finalized a root and which roots it was finalized over.
+#[cw_serde]
+pub struct Attestation {
+    /// Operators that voted for the root. Votes without an operator only count
+    /// towards `power_for`.
+    pub voters: Vec<Voter>,
+    pub power_for: Uint128,
+    /// Power that voted for another root of the chain.
+    pub power_against: Uint128,
+    /// Power of the full validator set when the root was finalized.
+    pub total_power: Uint128,
+    /// Every other root voted for the chain, highest power first.
+    pub competing_roots: Vec<RootTally>,
+}
+
+#[cw_serde]
+pub struct Voter {
+    pub operator: String,
+    pub power: Uint128,
+}
+
+#[cw_serde]
+pub struct RootTally {
+    pub root: SourceRoot,
+    pub power: Uint128,
+}
+
+/// PriceInfo is the stake-weighted median of the prices reported for a currency pair.
+#[cw_serde]
+pub struct PriceInfo {
+    pub price: Uint128,
+    /// Time of the block the price was aggregated at.
+    pub timestamp: Timestamp,
+    /// Height of the block the price was aggregated at.
+    pub height: u64,
+    /// Voting power that reported a price for the pair.
+    pub power: Uint128,
+}
+
+#[cw_serde]
+pub struct Ownership {
+    /// `None` once ownership has been renounced.
+    pub owner: Option<Addr>,
+    /// Address ownership has been offered to, until it accepts.
+    pub pending_owner: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct Config {
+    /// Number of seconds unbonded stake stays locked before it can be claimed.
+    pub unbonding_period: u64,
+    /// Where the voting power of a vote extension is taken from during aggregation.
+    pub power_source: PowerSource,
+    /// Weight applied to each staked denom when `power_source` is `Stake`.
+    /// Denoms without a weight do not contribute any power.
+    pub denom_weights: BTreeMap<String, Decimal>,
+    /// Fraction of the voting power that has to agree on a root for it to be
+    /// accepted, unless the chain overrides it.
+    pub default_threshold: Decimal,
+    /// How votes are aggregated for chains that do not override it.
+    pub default_strategy: Strategy,
+    /// Number of blocks a vote keeps counting towards quorum for chains that do not
+    /// override it. A window of one block only counts votes in the block they are cast.
+    pub default_vote_window: u64,
+    /// How long roots are kept for chains that do not override it.
+    pub default_retention: Retention,
+    /// Penalties for operators caught signing conflicting votes.
+    pub slashing: SlashingConfig,
+    /// Number of seconds a rotated operator key takes to replace the current one.
+    /// Votes signed with the current key can be submitted as evidence until then.
+    pub key_rotation_delay: u64,
+    /// How rewards are paid to operators. No rewards are paid when `None`.
+    pub rewards: Option<RewardsConfig>,
+    /// Penalties for operators that stop voting.
+    pub liveness: LivenessConfig,
+    /// Whether the votes behind every root finalized by vote are recorded.
+    pub record_attestations: bool,
+}
+
+#[cw_serde]
+pub struct LivenessConfig {
+    /// Number of most recent blocks liveness is judged over.
+    pub window: u64,
+    /// Fraction of the blocks of the window an operator has to vote in.
+    pub min_signed: Decimal,
+    /// Fraction of the stake of an operator slashed, and burned, when it is jailed
+    /// for downtime.
+    pub slash_fraction: Decimal,
+    /// Number of seconds an operator is jailed for downtime.
+    pub jail_duration: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct SigningInfo {
+    /// Height the current liveness window of the operator started at.
+    pub start_height: u64,
+    /// Number of blocks tracked since `start_height`.
+    pub index_offset: u64,
+    /// Number of blocks of the window the operator did not vote in.
+    pub missed_blocks: u64,
+    /// Window size `missed_blocks` was tracked with.
+    pub window: u64,
+    /// Number of roots finalized without the operator voting for their chain.
+    pub missed_roots: u64,
+    /// Number of votes for a root other than the one finalized for the chain.
+    pub minority_votes: u64,
+    /// Height of the last block the operator voted in.
+    pub last_voted_height: Option<u64>,
+}
+
+#[cw_serde]
+pub struct RewardsConfig {
+    /// Denom rewards are funded and paid in. Cannot be changed once set.
+    pub denom: String,
+    /// Maximum amount distributed out of the reward pool per block.
+    pub per_block: Uint128,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct OperatorRewards {
+    /// Rewards earned per share of the operator's stake since it first earned any.
+    pub reward_per_share: Decimal256,
+    /// Sum of the shares of every position with the operator.
+    pub total_shares: Uint128,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct RewardPosition {
+    /// Weighted power of the stake of the position, updated whenever the stake
+    /// changes.
+    pub shares: Uint128,
+    /// Reward per share of the operator the position was last settled at.
+    pub reward_per_share_paid: Decimal256,
+    /// Rewards settled but not claimed yet.
+    pub pending: Uint128,
+}
+
+#[cw_serde]
+pub struct OperatorInfo {
+    pub key: OperatorKey,
+    /// Key replacing `key` once its rotation delay has passed.
+    pub pending_key: Option<PendingKey>,
+    /// Fraction of the rewards of its delegators the operator keeps.
+    pub commission: Decimal,
+}
+
+impl OperatorInfo {
+    /// keys returns the keys signatures of the operator are accepted from at `time`,
+    /// the current one first.
+    pub fn keys(&self, time: Timestamp) -> Vec<&OperatorKey> {
+        match &self.pending_key {
+            Some(pending) if pending.active_at <= time => vec![&pending.key],
+            Some(pending) => vec![&self.key, &pending.key],
+            None => vec![&self.key],
+        }
+    }
+}
+
+#[cw_serde]
+pub struct OperatorKey {
+    pub key_type: KeyType,
+    pub pubkey: Binary,
+}
+
+#[cw_serde]
+pub struct PendingKey {
+    pub key: OperatorKey,
+    /// Time the key replaces the current key of the operator at.
+    pub active_at: Timestamp,
+}
+
+#[cw_serde]
+pub enum KeyType {
+    /// Compressed (33 byte) or uncompressed (65 byte) secp256k1 public key.
+    Secp256k1,
+    /// 32 byte ed25519 public key.
+    Ed25519,
+}
+
+impl std::fmt::Display for KeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyType::Secp256k1 => write!(f, "secp256k1"),
+            KeyType::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct SlashingConfig {
+    /// Fraction of the bonded and unbonding stake of an equivocating operator that is
+    /// slashed.
+    pub slash_fraction: Decimal,
+    /// Fraction of the slashed stake paid to whoever submitted the evidence. The rest
+    /// is burned.
+    pub reporter_bounty: Decimal,
+    /// Number of seconds an equivocating operator stays jailed.
+    pub jail_duration: u64,
+}
+
+/// ChainInfo describes a registered chain and how its roots are to be interpreted.
+#[cw_serde]
+pub struct ChainInfo {
+    pub family: ChainFamily,
+    /// CAIP-2 identifier of the chain, e.g. `eip155:1` or `cosmos:cosmoshub-4`.
+    pub caip2: String,
+    /// Hash function the roots of the chain are computed with.
+    pub hash_function: HashFunction,
+    /// What the roots of the chain commit to.
+    pub root_kind: RootKind,
+    /// Paused chains keep their roots but do not accept new ones.
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub enum ChainFamily {
+    Evm,
+    Cosmos,
+    Bitcoin,
+    Solana,
+    Other,
+}
+
+#[cw_serde]
+pub enum HashFunction {
+    Sha256,
+    Keccak256,
+    DoubleSha256,
+    Blake2b256,
+}
+
+#[cw_serde]
+pub enum RootKind {
+    StateRoot,
+    TransactionsRoot,
+    ReceiptsRoot,
+    AppHash,
+    BlockHash,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ChainConfig {
+    /// Overrides `Config::default_threshold` for this chain.
+    pub threshold: Option<Decimal>,
+    /// Overrides `Config::default_strategy` for this chain.
+    pub strategy: Option<Strategy>,
+    /// Overrides `Config::default_vote_window` for this chain.
+    pub vote_window: Option<u64>,
+    /// Overrides `Config::default_retention` for this chain.
+    pub retention: Option<Retention>,
+}
+
+/// Retention limits how long the roots of a chain can be looked up. A root expires
+/// as soon as any of the limits is exceeded.
+#[cw_serde]
+pub struct Retention {
+    /// Maximum number of roots kept for the chain.
+    pub max_roots: u64,
+    /// Maximum number of seconds since a root was finalized.
+    pub max_age_seconds: Option<u64>,
+    /// Maximum number of blocks since a root was finalized.
+    pub max_age_blocks: Option<u64>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum Strategy {
+    /// The root with the most power wins once it holds the threshold of the power
+    /// that voted for the chain.
+    #[default]
+    SupermajorityPlurality,
+    /// A root wins only if every vote for the chain agrees on it.
+    Unanimity,
+    /// The root with the most power wins once it holds the threshold of the power of
+    /// the full validator set.
+    WeightedThreshold,
+    /// Like `WeightedThreshold`, but operator votes always stay counted for `window`
+    /// blocks, regardless of the chain's vote window, so the first root to gather
+    /// enough power over that window wins.
+    FirstToQuorum { window: u64 },
+}
+
+#[cw_serde]
+pub struct PendingVote {
+    pub root: SourceRoot,
+    pub power: Uint128,
+    /// Block height the vote was cast at.
+    pub height: u64,
+}
+
+#[cw_serde]
+pub enum PowerSource {
+    /// Trust the `ve_power` reported alongside each vote extension.
+    VotePower,
+    /// Weigh each vote by the coins its operator has staked with this contract.
+    Stake,
+}
+
+#[cw_serde]
+pub struct Delegation {
+    pub delegator: Addr,
+    pub operator: Addr,
+    pub amount: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct Unbonding {
+    pub amount: Vec<Coin>,
+    pub release_at: Timestamp,
+}
+
+/// Reweight is the position of a pending recomputation of reward shares, which goes
+/// through every self-stake and then every delegation.
+#[cw_serde]
+pub enum Reweight {
+    Stakes {
+        start_after: Option<Addr>,
+    },
+    /// Delegations after `start_after`, keyed by (operator, delegator).
+    Delegations {
+        start_after: Option<(Addr, Addr)>,
+    },
+}
+
+/// SlashFactor tracks the share of the delegated stake of an operator left after every
+/// slash so far. A slash that takes everything starts a new epoch, in which the factor
+/// starts over from one.
+#[cw_serde]
+pub struct SlashFactor {
+    pub epoch: u64,
+    pub factor: Decimal256,
+}
+
+impl Default for SlashFactor {
+    fn default() -> Self {
+        SlashFactor {
+            epoch: 0,
+            factor: Decimal256::one(),
+        }
+    }
+}
+
+impl SlashFactor {
+    /// slash returns the factor left after taking `fraction` of the delegated stake.
+    pub fn slash(&self, fraction: Decimal) -> SlashFactor {
+        let factor = self.factor * (Decimal256::one() - Decimal256::from(fraction));
+        if factor.is_zero() {
+            return SlashFactor {
+                epoch: self.epoch + 1,
+                factor: Decimal256::one(),
+            };
+        }
+        SlashFactor {
+            epoch: self.epoch,
+            factor,
+        }
+    }
+
+    /// scale takes the slashes since `recorded` out of `coins`, rounding down and
+    /// dropping coins that reach zero.
+    pub fn scale(&self, recorded: &SlashFactor, coins: Vec<Coin>) -> Vec<Coin> {
+        if self.epoch != recorded.epoch {
+            return vec![];
+        }
+        if self.factor == recorded.factor {
+            return coins;
+        }
+        coins
+            .into_iter()
+            .filter_map(|mut coin| {
+                let scaled =
+                    Decimal256::from_ratio(coin.amount, 1u128) * self.factor / recorded.factor;
+                // never more than the recorded amount, so it always fits
+                coin.amount = scaled.to_uint_floor().try_into().ok()?;
+                (!coin.amount.is_zero()).then_some(coin)
+            })
+            .collect()
+    }
 }
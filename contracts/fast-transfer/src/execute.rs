@@ -8,7 +8,7 @@ use crate::{
     msg::FastTransfer,
     state::{AGGREGATOR_CONTRACT, BASE_TOKEN, LP_TOKEN_DENOM, PROCESSED_IDS},
 };
-use aggregator::aggregator::{LookupHashResponse, QueryMsg as AggQueryMsg};
+use aggregator::aggregator::{LookupRootResponse, QueryMsg as AggQueryMsg};
 use cosmwasm_std::{
     coins, Addr, BankMsg, Binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, Uint128,
 };
@@ -106,9 +106,9 @@ pub fn execute_fast_transfer(
 
     // Query the aggregator contract to check if the root hash exists in the keeper
     let aggregator_contract = AGGREGATOR_CONTRACT.load(deps.storage)?;
-    let _: LookupHashResponse = deps.querier.query_wasm_smart(
+    let _: LookupRootResponse = deps.querier.query_wasm_smart(
         aggregator_contract,
-        &AggQueryMsg::LookupHash {
+        &AggQueryMsg::LookupRoot {
             chain_id,
             hash: Binary(hex_decode(&sent_root_hash)?),
         },
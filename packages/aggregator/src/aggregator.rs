@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Binary, Timestamp, Uint128};
 
 #[cw_serde]
 #[derive(QueryResponses)]
@@ -7,9 +7,27 @@ pub enum QueryMsg {
     // GetCount returns the current count as a json-encoded number
     #[returns(LookupHashResponse)]
     LookupHash { chain_id: String, hash: Binary },
+    #[returns(LookupRootResponse)]
+    LookupRoot { chain_id: String, hash: Binary },
 }
 
+/// LookupHashResponse only holds the age of the root. Deployed consumers reject
+/// unknown fields, so root metadata is returned by `LookupRoot` instead.
 #[cw_serde]
 pub struct LookupHashResponse {
     pub age: u64,
 }
+
+#[cw_serde]
+pub struct LookupRootResponse {
+    /// Number of roots finalized for the chain since this one, including itself.
+    pub age: u64,
+    /// Height of the source chain block the root was taken from.
+    pub source_height: u64,
+    /// Height of the block the root was finalized at.
+    pub finalized_height: u64,
+    /// Time of the block the root was finalized at.
+    pub finalized_at: Timestamp,
+    /// Voting power that attested to the root. Zero for submitted roots.
+    pub power: Uint128,
+}
@@ -56,6 +56,7 @@ AVS_CONTRACT_PATH = config["AVS_CONTRACT"]
 FAST_TRANSFER_CONRACT_PATH = config["FAST_TRANSFER_CONTRACT"]
 
 BASE_DENOM = config["BASE_DENOM"]
+AVS_UNBONDING_PERIOD = config["AVS_UNBONDING_PERIOD"]
 
 MNEMONIC = config["MNEMONIC"]
 del config["MNEMONIC"]
@@ -85,12 +86,18 @@ def main():
         None,
     )
     print("AVS Contract Code ID: ", avs_contract_code_id)
+    avs_init_args = {
+        "unbonding_period": AVS_UNBONDING_PERIOD,
+        "power_source": "vote_power",
+        "denom_weights": {},
+        "default_threshold": "0.666666666666666666",
+    }
     avs_contract_address = instantiate_contract(
         client, 
         wallet, 
         str(wallet.address()),
         avs_contract_code_id, 
-        {}, 
+        avs_init_args, 
         "Slinky Plus Plus AVS", 
         "avs_contract"
     )